@@ -0,0 +1,229 @@
+use crate::{
+    config::settings::Settings,
+    sources::{ReplaySpeed, SimulatorOptions, SourceKind},
+};
+use chrono::NaiveTime;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::{path::PathBuf, time::Duration};
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Args {
+    /// What to do, the dashboard when nothing is given
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Alias or address of the sensor to connect to, overrides the remembered default device
+    #[arg(long, global = true, value_name = "ALIAS|ADDRESS", conflicts_with_all = ["serial", "replay", "simulate"])]
+    pub device: Option<String>,
+
+    /// Config file to use instead of the one in the platform config directory
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Most verbose level written to the log file in /tmp/co2nsole
+    #[arg(long, global = true, value_name = "LEVEL", default_value_t = tracing::Level::TRACE)]
+    pub log_level: tracing::Level,
+
+    /// Index or name (e.g. hci1) of the Bluetooth adapter to use, overrides the config file
+    #[arg(long, global = true, value_name = "INDEX|NAME")]
+    pub adapter: Option<String>,
+
+    /// Read measurements from the serial port (e.g. /dev/ttyUSB0) instead of BLE
+    #[arg(long, global = true, value_name = "PATH", conflicts_with = "replay")]
+    pub serial: Option<String>,
+
+    /// Baud rate of the serial port, matches the firmware's default
+    #[arg(long, global = true, default_value_t = 9600, requires = "serial")]
+    pub baud_rate: u32,
+
+    /// Append every received measurement to a JSON-lines file
+    #[arg(long, global = true, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// Play back a session previously saved with --record instead of connecting to a device
+    #[arg(long, global = true, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+
+    /// Replay pace: a multiplier of the original speed (1x, 10x) or "max"
+    #[arg(long, global = true, value_name = "SPEED", default_value_t = ReplaySpeed::default(), requires = "replay")]
+    pub replay_speed: ReplaySpeed,
+
+    /// Generate realistic measurements with a built-in simulated device
+    #[arg(long, global = true, conflicts_with_all = ["serial", "replay"])]
+    pub simulate: bool,
+
+    /// Seed of the simulator to make the simulated session reproducible
+    #[arg(long, global = true, requires = "simulate")]
+    pub seed: Option<u64>,
+
+    /// Let the simulated device randomly drop measurements and break its CO2 sensor
+    #[arg(long, global = true, requires = "simulate")]
+    pub simulate_faults: bool,
+
+    /// Time of the day the simulation starts at, 08:00 with --seed and the current time without
+    #[arg(long, global = true, value_name = "HH:MM", value_parser = parse_time, requires = "simulate")]
+    pub simulate_start: Option<NaiveTime>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Show the dashboard, the default
+    Tui,
+    /// Print every measurement as a line until interrupted
+    Watch {
+        /// One JSON object per line instead of the human readable text
+        #[arg(long)]
+        json: bool,
+    },
+    /// List the co2nsole devices in range and pick the one to connect to by default
+    Scan {
+        /// Print the available Bluetooth adapters instead
+        #[arg(long)]
+        adapters: bool,
+    },
+    /// Calibrate a sensor of the device without the dashboard, or list the past calibrations
+    Calibrate {
+        #[command(subcommand)]
+        target: CalibrateTarget,
+    },
+    /// Write the measurements to a file or the standard output
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// File to write to, the standard output when not given
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+        /// Stop after this many measurements
+        #[arg(long)]
+        count: Option<usize>,
+        /// Stop after this long, e.g. 90s, 5m or 2h
+        #[arg(long, value_parser = parse_duration)]
+        duration: Option<Duration>,
+    },
+    /// Keep the devices connected without the dashboard, for the notifications and recording
+    Daemon,
+    /// Print the latest measurement of the device and exit, fails when none arrives in time
+    Status {
+        /// A JSON object instead of the human readable text
+        #[arg(long)]
+        json: bool,
+        /// How long to wait for the measurement, e.g. 30s or 2m
+        #[arg(long, value_parser = parse_duration, default_value = "60s")]
+        timeout: Duration,
+    },
+    /// Inspect or create the config file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CalibrateTarget {
+    /// Take the fresh air as the 400 ppm point, waits until the readings settled
+    Co2 {
+        /// The sensor has been in fresh air for long enough, calibrate right away
+        #[arg(long)]
+        now: bool,
+    },
+    /// Adjust the temperature to the reading of a reference thermometer, in the configured unit
+    Temperature {
+        #[arg(allow_negative_numbers = true)]
+        reference: f32,
+        /// Whole °C the device adds now, needed when it was calibrated from another machine
+        #[arg(long, allow_negative_numbers = true)]
+        current_adjust: Option<i32>,
+    },
+    /// Adjust the humidity to the reading of a reference hygrometer in %
+    Humidity {
+        reference: f32,
+        /// Whole percents the device adds now, needed when it was calibrated from another
+        /// machine
+        #[arg(long, allow_negative_numbers = true)]
+        current_adjust: Option<i32>,
+    },
+    /// Print every calibration sent to the devices
+    Log,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    /// The same JSON lines as --record writes
+    Json,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigAction {
+    /// Print the config in use, the values missing from the file are the defaults
+    Show,
+    /// Write the config file with every default spelled out, ready to be edited
+    Init {
+        /// Rewrite an existing config file, the values already set in it are kept
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Parses durations like `90s`, `5m` or `2h`, a plain number is in seconds
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (value, unit) = match s.find(|char: char| !char.is_ascii_digit()) {
+        Some(index) => s.split_at(index),
+        None => (s, "s"),
+    };
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        _ => {
+            return Err(format!(
+                "invalid duration {s:?}, expected e.g. 90s, 5m or 2h"
+            ))
+        }
+    };
+
+    value
+        .parse::<u64>()
+        .map(|value| Duration::from_secs(value * seconds))
+        .map_err(|_| format!("invalid duration {s:?}, expected e.g. 90s, 5m or 2h"))
+}
+
+fn parse_time(s: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(s, "%H:%M")
+        .map_err(|_| format!("invalid time {s:?}, expected e.g. 08:00 or 21:30"))
+}
+
+impl Args {
+    pub fn source_kind(&self, settings: &Settings) -> SourceKind {
+        if self.simulate {
+            return SourceKind::Simulator(SimulatorOptions {
+                seed: self.seed,
+                faults: self.simulate_faults,
+                start: self.simulate_start,
+            });
+        }
+
+        if let Some(path) = &self.replay {
+            return SourceKind::Replay {
+                path: path.clone(),
+                speed: self.replay_speed,
+                device: None,
+            };
+        }
+
+        if let Some(path) = &self.serial {
+            return SourceKind::Serial {
+                path: path.clone(),
+                baud_rate: self.baud_rate,
+            };
+        }
+
+        match self.device.as_ref().or(settings.default_device.as_ref()) {
+            Some(device) => SourceKind::BleDevice {
+                address: settings.resolve_device(device),
+            },
+            None => SourceKind::Ble,
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{
+    bluetooth::{Connection, FromBleData},
+    error::{Error, Result},
+};
+use btleplug::api::{bleuuid::uuid_from_u16, Peripheral};
+
+/// Standard Bluetooth SIG Battery Service and its Battery Level characteristic
+const BATTERY_SERVICE: u16 = 0x180F;
+const BATTERY_LEVEL_CHAR: u16 = 0x2A19;
+
+/// Charge in percent, 0-100
+pub struct BatteryLevel(pub u8);
+
+impl FromBleData for BatteryLevel {
+    fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        match data.first() {
+            Some(level) if *level <= 100 => Ok(Self(*level)),
+            _ => Err(Error::Decode {
+                payload: data,
+                reason: "battery level must be a single byte percentage".to_string(),
+            }),
+        }
+    }
+}
+
+async fn read_and_listen(
+    connection: &Connection<impl Peripheral>,
+    on_level: &mut (dyn FnMut(u8) + Send),
+) -> Result<()> {
+    let char_uuid = uuid_from_u16(BATTERY_LEVEL_CHAR);
+    // the level is only notified when it changes, which may take hours
+    match connection.read_from_sensor(char_uuid).await {
+        Ok(BatteryLevel(level)) => on_level(level),
+        // the notifications may still bring a valid one
+        Err(e @ Error::Decode { .. }) => tracing::warn!(
+            "Skipping the battery level of {}: {e}",
+            connection.address()
+        ),
+        Err(e) => return Err(e),
+    }
+
+    connection
+        .listen(char_uuid, |BatteryLevel(level)| on_level(level))
+        .await
+}
+
+/// Reports the battery level of the peripheral for as long as the connection lives. The
+/// battery is optional, so a peripheral without it or a failure never ends the connection.
+pub async fn watch(
+    connection: &Connection<impl Peripheral>,
+    on_level: &mut (dyn FnMut(u8) + Send),
+) -> Result<()> {
+    if !connection.has_service(uuid_from_u16(BATTERY_SERVICE)) {
+        tracing::debug!("{} has no battery", connection.address());
+    } else if let Err(e) = read_and_listen(connection, on_level).await {
+        tracing::warn!(
+            "Failed to watch the battery of {}: {e}",
+            connection.address()
+        );
+    }
+
+    std::future::pending().await
+}
+
+/// Notifies the user when the level dropped to one of the alert thresholds since the
+/// previous report. The very first report of an already low battery alerts as well.
+pub fn check_level(device: &str, previous: Option<u8>, level: u8, thresholds: &[u8]) {
+    let crossed = thresholds
+        .iter()
+        .any(|threshold| level <= *threshold && previous.is_none_or(|p| p > *threshold));
+    if !crossed {
+        return;
+    }
+
+    tracing::warn!("Battery of {device} is low: {level}%");
+    if let Err(e) = notify_rust::Notification::new()
+        .summary(&format!("{device} battery is low"))
+        .body(&format!("{level}% left, time to charge it"))
+        .show()
+    {
+        tracing::error!("Failed to show the low battery notification: {e}");
+    }
+}
@@ -1,35 +1,156 @@
-use std::{error::Error, str::FromStr};
-
-use crate::bluetooth::Connection;
-use crate::config::*;
+use crate::bluetooth::{Connection, FromBleData};
+use crate::calibration::{Adjustment, CalibrationState};
+use crate::config;
+use crate::error::{Error, Result};
 use btleplug::api::Peripheral;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 use uuid::Uuid;
 
-#[allow(dead_code)]
+/// Receives the outcome of a calibration, dropped without an answer by the sources which
+/// can not be calibrated
+pub type CalibrationReply = oneshot::Sender<Result<()>>;
+
 pub enum BleAction {
-    CalibrateCo2,
-    CalibrateTemperature(i32),
+    /// Takes the current CO2 level as the 400 ppm baseline
+    CalibrateCo2 {
+        reply: CalibrationReply,
+    },
+    /// Whole degrees the firmware adds to every temperature reading
+    CalibrateTemperature {
+        adjust: i32,
+        reply: CalibrationReply,
+    },
+    /// Whole percents the firmware adds to every humidity reading
+    CalibrateHumidity {
+        adjust: i32,
+        reply: CalibrationReply,
+    },
     Stop,
 }
 
+/// Value of a calibration characteristic: `isCalibrated()` as a 32 bit integer, followed by
+/// the result of `calibrate()` once something was written, see `core/src/ble.cpp`
+pub struct CalibrationStatus {
+    pub is_calibrated: bool,
+    pub last_write: Option<bool>,
+}
+
+impl FromBleData for CalibrationStatus {
+    fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        let (is_calibrated, last_write) = match *data.as_slice() {
+            [a, b, c, d] => (i32::from_le_bytes([a, b, c, d]), None),
+            [a, b, c, d, result] => (i32::from_le_bytes([a, b, c, d]), Some(result != 0)),
+            _ => {
+                return Err(Error::Decode {
+                    payload: data,
+                    reason: "calibration status must be a 32 bit integer and a result byte"
+                        .to_string(),
+                })
+            }
+        };
+
+        Ok(Self {
+            is_calibrated: is_calibrated != 0,
+            last_write,
+        })
+    }
+}
+
+/// Writes the calibration payload and reads back whether the firmware accepted it
+async fn calibrate<TPeripheral: Peripheral>(
+    connection: &Connection<TPeripheral>,
+    char_uuid: Uuid,
+    payload: &[u8],
+    name: &'static str,
+) -> Result<()> {
+    connection.write_to_sennsor(payload, char_uuid).await?;
+
+    let status: CalibrationStatus = connection.read_from_sensor(char_uuid).await?;
+    // older firmware replaced the value with the result of the write
+    if !status.last_write.unwrap_or(status.is_calibrated) {
+        return Err(Error::CalibrationRejected(name));
+    }
+
+    Ok(())
+}
+
+/// `None` when the firmware is too old to have the characteristic
+async fn read_status<TPeripheral: Peripheral>(
+    connection: &Connection<TPeripheral>,
+    char_uuid: Uuid,
+) -> Result<Option<bool>> {
+    if !connection.has_characteristic(char_uuid) {
+        return Ok(None);
+    }
+
+    let status: CalibrationStatus = connection.read_from_sensor(char_uuid).await?;
+    Ok(Some(status.is_calibrated))
+}
+
+/// Reads whether the sensors were calibrated, the firmware does not report the offsets
+pub async fn read_calibration<TPeripheral: Peripheral>(
+    connection: &Connection<TPeripheral>,
+) -> Result<CalibrationState> {
+    let adjustment = |is_calibrated: Option<bool>| {
+        is_calibrated.map(|is_calibrated| Adjustment {
+            is_calibrated,
+            adjust: None,
+        })
+    };
+
+    Ok(CalibrationState {
+        co2: read_status(connection, config::ble().co2_calibration_char).await?,
+        temperature: adjustment(
+            read_status(connection, config::ble().temperature_calibration_char).await?,
+        ),
+        humidity: adjustment(
+            read_status(connection, config::ble().humidity_calibration_char).await?,
+        ),
+    })
+}
+
 pub async fn run_ble_mpsc<TPeripheral: Peripheral>(
     connection: &Connection<TPeripheral>,
     mut ble_action_receiver: mpsc::Receiver<BleAction>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<()> {
     while let Some(action) = ble_action_receiver.recv().await {
         match action {
-            BleAction::CalibrateCo2 => {
+            BleAction::CalibrateCo2 { reply } => {
                 tracing::info!("Calibrating CO2 sensor");
-                connection
-                    .write_to_sennsor(
-                        "what the fuck".as_bytes(),
-                        Uuid::from_str(&BLE_MAIN_SENSOR_CO2_CALIBRATION_CHAR).unwrap(),
-                    )
-                    .await?
+                let result = calibrate(
+                    connection,
+                    config::ble().co2_calibration_char,
+                    config::ble().calibrate_co2_command.as_bytes(),
+                    "CO2",
+                )
+                .await;
+
+                let _ = reply.send(result);
             }
-            BleAction::CalibrateTemperature(_temperature) => {
-                tracing::info!("Calibrating temperature sensor");
+            BleAction::CalibrateTemperature { adjust, reply } => {
+                tracing::info!("Calibrating temperature sensor, adjust by {adjust}");
+                let result = calibrate(
+                    connection,
+                    config::ble().temperature_calibration_char,
+                    &adjust.to_le_bytes(),
+                    "temperature",
+                )
+                .await;
+
+                // a broken connection shows up in the stream as well, nothing to do here
+                let _ = reply.send(result);
+            }
+            BleAction::CalibrateHumidity { adjust, reply } => {
+                tracing::info!("Calibrating humidity sensor, adjust by {adjust}");
+                let result = calibrate(
+                    connection,
+                    config::ble().humidity_calibration_char,
+                    &adjust.to_le_bytes(),
+                    "humidity",
+                )
+                .await;
+
+                let _ = reply.send(result);
             }
             BleAction::Stop => {
                 connection.disconnect().await?;
@@ -41,3 +162,31 @@ pub async fn run_ble_mpsc<TPeripheral: Peripheral>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_after_boot_has_no_write_result() {
+        let status = CalibrationStatus::from_bytes(1i32.to_le_bytes().to_vec()).unwrap();
+        assert!(status.is_calibrated);
+        assert_eq!(status.last_write, None);
+    }
+
+    #[test]
+    fn rejected_write_keeps_the_calibration_state() {
+        let mut value = 1i32.to_le_bytes().to_vec();
+        value.push(0);
+
+        let status = CalibrationStatus::from_bytes(value).unwrap();
+        assert!(status.is_calibrated);
+        assert_eq!(status.last_write, Some(false));
+    }
+
+    #[test]
+    fn status_of_another_size_is_rejected() {
+        assert!(CalibrationStatus::from_bytes(vec![1, 0]).is_err());
+        assert!(CalibrationStatus::from_bytes(vec![1, 0, 0, 0, 1, 0]).is_err());
+    }
+}
@@ -1,8 +1,17 @@
-use btleplug::api::{Central, CharPropFlags, Manager as _, Peripheral, ScanFilter, WriteType};
-use btleplug::platform::Manager;
+use crate::{
+    connection::{ConnectionState, StateListener},
+    error::{Error, Result},
+    framing::NotificationFramer,
+};
+use btleplug::api::{
+    BDAddr, Central, CharPropFlags, Manager as _, Peripheral, ScanFilter, WriteType,
+};
+use btleplug::platform::{Adapter, Manager, Peripheral as PlatformPeripheral};
 use futures::StreamExt;
-use std::error::Error;
-use std::time::Duration;
+use lazy_static::lazy_static;
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
 use tokio::time::{self, sleep, timeout};
 use uuid::Uuid;
 
@@ -12,15 +21,99 @@ pub struct Connection<TPeripheral: Peripheral> {
 }
 
 pub trait FromBleData {
-    fn from_bytes(data: Vec<u8>) -> Result<Self, Box<dyn Error>>
+    /// Whether a message may be split over several notifications, see [`NotificationFramer`]
+    const FRAGMENTED: bool = false;
+
+    fn from_bytes(data: Vec<u8>) -> Result<Self>
     where
         Self: Sized;
 }
 
+/// Host side quality of the link to a device, the counterpart of the health metrics the
+/// firmware exports (`PROM_METRIC_RSSI`, `PROM_METRIC_SEND_FAILURES`)
+#[derive(Debug, Default, Clone)]
+pub struct LinkQuality {
+    pub rssi: Option<i16>,
+    pub notifications: u64,
+    pub decode_failures: u64,
+    /// Notifications thrown away because the rest of their message never came
+    pub dropped_fragments: u64,
+    pub reconnects: u64,
+    last_notification: Option<Instant>,
+    /// Gaps between the latest notifications, the oldest first
+    intervals: VecDeque<Duration>,
+}
+
+/// How many notification gaps are kept to compute the rate
+const LINK_INTERVALS: usize = 30;
+/// RSSI is not pushed by the peripheral, so it is polled not more often than this
+const RSSI_INTERVAL: Duration = Duration::from_secs(10);
+
+impl LinkQuality {
+    pub fn mean_interval(&self) -> Option<Duration> {
+        let count = self.intervals.len() as u32;
+        (count > 0).then(|| self.intervals.iter().sum::<Duration>() / count)
+    }
+
+    pub fn max_interval(&self) -> Option<Duration> {
+        self.intervals.iter().max().copied()
+    }
+}
+
+/// Collects the [`LinkQuality`] of a device across its connections
+#[derive(Debug, Default)]
+pub struct LinkStats {
+    quality: Mutex<LinkQuality>,
+}
+
+impl LinkStats {
+    pub fn snapshot(&self) -> LinkQuality {
+        self.quality.lock().unwrap().clone()
+    }
+
+    pub fn record_notification(&self) {
+        let mut quality = self.quality.lock().unwrap();
+        let now = Instant::now();
+
+        if let Some(previous) = quality.last_notification.replace(now) {
+            if quality.intervals.len() == LINK_INTERVALS {
+                quality.intervals.pop_front();
+            }
+            quality.intervals.push_back(now - previous);
+        }
+        quality.notifications += 1;
+    }
+
+    pub fn record_decode_failure(&self) {
+        self.quality.lock().unwrap().decode_failures += 1;
+    }
+
+    pub fn record_dropped_fragments(&self, count: usize) {
+        self.quality.lock().unwrap().dropped_fragments += count as u64;
+    }
+
+    pub fn record_rssi(&self, rssi: i16) {
+        self.quality.lock().unwrap().rssi = Some(rssi);
+    }
+
+    /// A new connection starts measuring the gaps from scratch
+    pub fn record_reconnect(&self) {
+        let mut quality = self.quality.lock().unwrap();
+        quality.reconnects += 1;
+        quality.last_notification = None;
+    }
+}
+
 const TIMEOUT: Duration = Duration::from_secs(10);
+/// Silence after which a still connected peripheral is considered hung and reconnected
+const STALE_TIMEOUT: Duration = Duration::from_secs(60);
 
 impl<TPer: Peripheral> Connection<TPer> {
-    pub async fn disconnect(&self) -> Result<(), Box<dyn Error>> {
+    pub fn address(&self) -> String {
+        peripheral_address(&self.peripheral)
+    }
+
+    pub async fn disconnect(&self) -> Result<()> {
         tracing::debug!("Disconnecting from sensor");
         if let Some(characteristic) = &self.subscribed_characteristic {
             self.peripheral.unsubscribe(characteristic).await?;
@@ -58,29 +151,55 @@ impl<TPer: Peripheral> Connection<TPer> {
         }
     }
 
+    pub fn has_service(&self, service_uuid: Uuid) -> bool {
+        self.peripheral
+            .services()
+            .iter()
+            .any(|service| service.uuid == service_uuid)
+    }
+
+    pub fn has_characteristic(&self, char_uuid: Uuid) -> bool {
+        self.peripheral
+            .characteristics()
+            .iter()
+            .any(|characteristic| characteristic.uuid == char_uuid)
+    }
+
     fn try_find_characteristic(
         &self,
         char_uuid: Uuid,
         property: CharPropFlags,
-    ) -> Result<btleplug::api::Characteristic, Box<dyn Error>> {
+    ) -> Result<btleplug::api::Characteristic> {
         for characteristic in self.peripheral.characteristics().into_iter() {
             if characteristic.uuid == char_uuid && characteristic.properties.contains(property) {
                 return Ok(characteristic);
             }
         }
 
-        Err(format!(
-            "Failed to connect to ble device. {} characteristic not found",
-            char_uuid
-        )
-        .into())
+        Err(Error::CharacteristicNotFound {
+            uuid: char_uuid,
+            property,
+        })
+    }
+
+    async fn update_rssi(&self, link: &LinkStats) {
+        match self.peripheral.properties().await {
+            Ok(Some(properties)) => {
+                if let Some(rssi) = properties.rssi {
+                    link.record_rssi(rssi);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to read RSSI: {e}"),
+        }
     }
 
     pub async fn subscribe<TData: FromBleData, TFun: FnMut(TData)>(
         &self,
         char_uuid: Uuid,
+        link: &LinkStats,
         mut fun: TFun,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<()> {
         tracing::debug!("Subscribing to sensor");
 
         let characteristic = self.try_find_characteristic(char_uuid, CharPropFlags::NOTIFY)?;
@@ -88,41 +207,100 @@ impl<TPer: Peripheral> Connection<TPer> {
         //self.subscribed_characteristic = Some(characteristic);
 
         let mut notification_stream = self.peripheral.notifications().await?;
+        let mut last_notification = Instant::now();
+        let mut last_rssi_update: Option<Instant> = None;
+        let mut framer = TData::FRAGMENTED.then(NotificationFramer::new);
+
+        loop {
+            match timeout(TIMEOUT, notification_stream.next()).await {
+                // the stream has the notifications of all the subscribed characteristics
+                Ok(Some(data)) if data.uuid != char_uuid => {}
+                Ok(Some(data)) => {
+                    tracing::debug!("Received data from sensor {data:?}");
+                    last_notification = Instant::now();
+                    link.record_notification();
+                    let messages = match &mut framer {
+                        Some(framer) => {
+                            let (messages, dropped) = framer.push(data.value);
+                            if dropped > 0 {
+                                link.record_dropped_fragments(dropped);
+                            }
+                            messages
+                        }
+                        None => vec![data.value],
+                    };
+
+                    for message in messages {
+                        match TData::from_bytes(message) {
+                            Ok(data) => fun(data),
+                            Err(e) => {
+                                link.record_decode_failure();
+                                tracing::error!("Error decodring data from sensor {}", e)
+                            }
+                        }
+                    }
 
-        while let Some(data) = timeout(TIMEOUT, notification_stream.next()).await? {
-            tracing::debug!("Received data from sensor {data:?}");
-            match TData::from_bytes(data.value) {
-                Ok(data) => fun(data),
-                Err(e) => tracing::error!("Error decodring data from sensor {}", e),
+                    if last_rssi_update.is_none_or(|at| at.elapsed() > RSSI_INTERVAL) {
+                        self.update_rssi(link).await;
+                        last_rssi_update = Some(Instant::now());
+                    }
+                }
+                Ok(None) => return Err(Error::ConnectionLost),
+                // a gap in the notifications is not fatal on its own, only the lost
+                // connection or a long silence is
+                Err(_) if last_notification.elapsed() < STALE_TIMEOUT => {
+                    tracing::warn!(
+                        "No notifications from sensor for {}s",
+                        last_notification.elapsed().as_secs()
+                    );
+                }
+                Err(_) => return Err(Error::NoData(last_notification.elapsed())),
             }
 
             let is_connected = timeout(TIMEOUT, self.peripheral.is_connected())
                 .await
-                .map_err(|_| "Connection lost")??;
+                .map_err(|_| Error::Timeout("checking the BLE connection"))??;
 
             if !is_connected {
-                return Err("BLE connection was lost".into());
+                return Err(Error::ConnectionLost);
             }
         }
-
-        Ok(())
     }
 
-    pub async fn read_from_sensor<TData: FromBleData>(
+    /// Subscribes to a characteristic which notifies rarely (e.g. the battery level), so
+    /// unlike [`Connection::subscribe`] it does not treat the silence as a broken link
+    pub async fn listen<TData: FromBleData, TFun: FnMut(TData)>(
         &self,
         char_uuid: Uuid,
-    ) -> Result<TData, Box<dyn Error>> {
+        mut fun: TFun,
+    ) -> Result<()> {
+        let characteristic = self.try_find_characteristic(char_uuid, CharPropFlags::NOTIFY)?;
+        self.peripheral.subscribe(&characteristic).await?;
+
+        let mut notification_stream = self.peripheral.notifications().await?;
+        while let Some(data) = notification_stream.next().await {
+            if data.uuid != char_uuid {
+                continue;
+            }
+
+            // a single bad value must not stop the updates for the rest of the connection
+            match TData::from_bytes(data.value) {
+                Ok(data) => fun(data),
+                Err(e) => tracing::warn!("Skipping a notification of {char_uuid}: {e}"),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn read_from_sensor<TData: FromBleData>(&self, char_uuid: Uuid) -> Result<TData> {
         tracing::debug!("Reading sensor");
 
         let characteristic = self.try_find_characteristic(char_uuid, CharPropFlags::READ)?;
         TData::from_bytes(self.peripheral.read(&characteristic).await?)
     }
 
-    pub async fn write_to_sennsor(
-        &self,
-        data: &[u8],
-        char_uuid: Uuid,
-    ) -> Result<(), Box<dyn Error>> {
+    pub async fn write_to_sennsor(&self, data: &[u8], char_uuid: Uuid) -> Result<()> {
         tracing::debug!("Writing to sensor");
 
         let characteristic = self.try_find_characteristic(char_uuid, CharPropFlags::WRITE)?;
@@ -134,24 +312,109 @@ impl<TPer: Peripheral> Connection<TPer> {
     }
 }
 
-pub async fn connect_to(
-    name: &str,
-    service_uuid: Uuid,
-) -> Result<Connection<impl Peripheral>, Box<dyn Error>> {
+pub struct DiscoveredPeripheral {
+    pub address: String,
+    pub local_name: String,
+}
+
+lazy_static! {
+    /// Scans started and stopped concurrently by different device tasks interfere with each
+    /// other on the same adapter, so only one scan is allowed at a time.
+    static ref SCAN_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
+/// Stable identifier of the peripheral. macOS does not expose MAC addresses so the
+/// platform id is used there instead.
+pub fn peripheral_address(peripheral: &impl Peripheral) -> String {
+    let address = peripheral.address();
+    if address == BDAddr::default() {
+        peripheral.id().to_string()
+    } else {
+        address.to_string()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AdapterError {
+    /// The OS does not expose any Bluetooth adapter
+    #[error("No Bluetooth adapters found")]
+    NoAdapters,
+    /// None of the adapters matches the configured index or name
+    #[error("Bluetooth adapter {selector:?} not found. Available adapters: {}", available.join(", "))]
+    NotFound {
+        selector: String,
+        available: Vec<String>,
+    },
+}
+
+/// Index or name (e.g. `hci1`) of the adapter to use instead of the first one
+static PREFERRED_ADAPTER: OnceLock<String> = OnceLock::new();
+
+pub fn prefer_adapter(selector: String) {
+    if PREFERRED_ADAPTER.set(selector).is_err() {
+        tracing::warn!("Bluetooth adapter was already selected");
+    }
+}
+
+fn adapter_matches(info: &str, selector: &str) -> bool {
+    // linux reports adapters as "hci0 (usb:v1D6Bp0246d0537)"
+    info == selector || info.split_whitespace().next() == Some(selector)
+}
+
+/// Descriptions of all the adapters in the order of their indexes
+pub async fn list_adapters() -> Result<Vec<String>> {
+    let manager = Manager::new().await?;
+    let mut infos = Vec::new();
+
+    for adapter in manager.adapters().await? {
+        infos.push(adapter.adapter_info().await?);
+    }
+
+    Ok(infos)
+}
+
+async fn select_adapter() -> Result<Adapter> {
     let manager = Manager::new().await?;
     let adapter_list = manager.adapters().await?;
     if adapter_list.is_empty() {
-        panic!("No Bluetooth adapters found");
+        return Err(AdapterError::NoAdapters.into());
     }
 
-    let adapter = adapter_list
-        .into_iter()
-        .next()
-        .expect("No Bluetooth adapters found");
+    let selector = match PREFERRED_ADAPTER.get() {
+        Some(selector) => selector,
+        None => return Ok(adapter_list.into_iter().next().unwrap()),
+    };
+
+    let mut available = Vec::new();
+    for (index, adapter) in adapter_list.into_iter().enumerate() {
+        let info = adapter.adapter_info().await?;
+        if selector.parse::<usize>() == Ok(index) || adapter_matches(&info, selector) {
+            return Ok(adapter);
+        }
+
+        available.push(format!("{index}: {info}"));
+    }
+
+    Err(AdapterError::NotFound {
+        selector: selector.clone(),
+        available,
+    }
+    .into())
+}
+
+/// Makes sure that the configured adapter exists, returns its description
+pub async fn check_adapter() -> Result<String> {
+    let adapter = select_adapter().await?;
+    Ok(adapter.adapter_info().await?)
+}
 
+async fn scan(service_uuids: Vec<Uuid>) -> Result<Vec<PlatformPeripheral>> {
+    let adapter = select_adapter().await?;
+
+    let _scan_guard = SCAN_LOCK.lock().await;
     adapter
         .start_scan(ScanFilter {
-            services: vec![service_uuid],
+            services: service_uuids,
         })
         .await?;
 
@@ -160,63 +423,234 @@ pub async fn connect_to(
 
     time::sleep(Duration::from_secs(2)).await;
     let peripherals = adapter.peripherals().await?;
+    adapter.stop_scan().await?;
 
     if peripherals.is_empty() {
         tracing::error!("No BLE peripherals found")
-    } else {
-        // All peripheral devices in range.
-        for peripheral in peripherals.into_iter() {
-            let properties = peripheral.properties().await?;
-            let is_connected = peripheral.is_connected().await?;
-            let local_name = properties
-                .unwrap()
-                .local_name
-                .unwrap_or(String::from("(peripheral name unknown)"));
-            tracing::debug!("Connected to peripheral {:?}.", &local_name);
-
-            // Check if it's the peripheral we want.
-            if local_name.contains(name) {
-                if !is_connected {
-                    // Connect if we aren't already connected.
-                    if let Err(err) = peripheral.connect().await {
-                        tracing::error!(?err, "Error connecting to peripheral, skipping");
-                        continue;
-                    }
-                }
+    }
 
-                let is_connected = peripheral.is_connected().await?;
-                tracing::debug!(
-                    "Connected ({:?}) to peripheral {:?}.",
-                    is_connected,
-                    &local_name
-                );
+    Ok(peripherals)
+}
 
-                if is_connected {
-                    peripheral.discover_services().await?;
-
-                    if peripheral
-                        .services()
-                        .iter()
-                        .any(|service| service.uuid == service_uuid)
-                    {
-                        adapter.stop_scan().await?;
-                        return Ok(Connection {
-                            peripheral,
-                            subscribed_characteristic: None,
-                        });
-                    }
+async fn local_name(peripheral: &impl Peripheral) -> Result<String> {
+    Ok(peripheral
+        .properties()
+        .await?
+        .and_then(|properties| properties.local_name)
+        .unwrap_or(String::from("(peripheral name unknown)")))
+}
 
-                    tracing::debug!(
-                        "Peripheral {:?} does not have the required characteristic.",
-                        &local_name
-                    );
-                    peripheral.disconnect().await?;
-                }
+/// Connects to the peripheral and verifies that it exposes the required service
+async fn try_connect<TPeripheral: Peripheral>(
+    peripheral: TPeripheral,
+    local_name: &str,
+    service_uuid: Uuid,
+    on_state: &StateListener,
+) -> Result<Option<Connection<TPeripheral>>> {
+    if !peripheral.is_connected().await? {
+        on_state(ConnectionState::Connecting);
+        // Connect if we aren't already connected.
+        if let Err(err) = peripheral.connect().await {
+            tracing::error!(?err, "Error connecting to peripheral, skipping");
+            return Ok(None);
+        }
+    }
+
+    let is_connected = peripheral.is_connected().await?;
+    tracing::debug!(
+        "Connected ({:?}) to peripheral {:?}.",
+        is_connected,
+        &local_name
+    );
+
+    if is_connected {
+        on_state(ConnectionState::Discovering);
+        peripheral.discover_services().await?;
+
+        if peripheral
+            .services()
+            .iter()
+            .any(|service| service.uuid == service_uuid)
+        {
+            return Ok(Some(Connection {
+                peripheral,
+                subscribed_characteristic: None,
+            }));
+        }
+
+        tracing::debug!(
+            "Peripheral {:?} does not have the required characteristic.",
+            &local_name
+        );
+        peripheral.disconnect().await?;
+    }
+
+    Ok(None)
+}
+
+/// Lists all the peripherals in range whose name contains `name` and that advertise the service
+pub async fn discover(name: &str, service_uuid: Uuid) -> Result<Vec<DiscoveredPeripheral>> {
+    let mut discovered = Vec::new();
+    let peripherals = scan(vec![service_uuid]).await?;
+
+    for peripheral in peripherals {
+        let properties = match peripheral.properties().await? {
+            Some(properties) => properties,
+            None => continue,
+        };
+
+        let local_name = properties.local_name.unwrap_or_default();
+        // the window sensor shares the name prefix, so also check the advertised services
+        // when the peripheral tells us about them
+        let advertises_service =
+            properties.services.is_empty() || properties.services.contains(&service_uuid);
+
+        if local_name.contains(name) && advertises_service {
+            discovered.push(DiscoveredPeripheral {
+                address: peripheral_address(&peripheral),
+                local_name,
+            });
+        }
+    }
+
+    Ok(discovered)
+}
+
+/// Everything we could learn about a peripheral during a scan
+pub struct ScannedPeripheral {
+    pub address: String,
+    pub local_name: String,
+    pub rssi: Option<i16>,
+    pub is_connected: bool,
+    pub services: Vec<Uuid>,
+    /// Populated only if the peripheral accepted a connection
+    pub characteristics: Result<Vec<(Uuid, CharPropFlags)>, String>,
+}
+
+async fn discover_characteristics(
+    peripheral: &impl Peripheral,
+    was_connected: bool,
+) -> Result<Vec<(Uuid, CharPropFlags)>> {
+    if !was_connected {
+        timeout(TIMEOUT, peripheral.connect())
+            .await
+            .map_err(|_| Error::Timeout("connecting"))??;
+    }
+
+    timeout(TIMEOUT, peripheral.discover_services())
+        .await
+        .map_err(|_| Error::Timeout("discovering services"))??;
+    let characteristics = peripheral
+        .characteristics()
+        .into_iter()
+        .map(|characteristic| (characteristic.uuid, characteristic.properties))
+        .collect();
+
+    // leave the peripheral in the state we found it
+    if !was_connected {
+        peripheral.disconnect().await?;
+    }
+
+    Ok(characteristics)
+}
+
+/// Lists every peripheral in range which either advertises one of the services or has a name
+/// containing `name`, connecting to each of them to find out the characteristics
+pub async fn scan_peripherals(
+    name: &str,
+    service_uuids: &[Uuid],
+) -> Result<Vec<ScannedPeripheral>> {
+    let mut scanned = Vec::new();
+    let peripherals = scan(service_uuids.to_vec()).await?;
+
+    for peripheral in peripherals {
+        let properties = match peripheral.properties().await? {
+            Some(properties) => properties,
+            None => continue,
+        };
+
+        let local_name = properties.local_name.unwrap_or_default();
+        let advertises_service = properties
+            .services
+            .iter()
+            .any(|service| service_uuids.contains(service));
+
+        if !advertises_service && !local_name.contains(name) {
+            continue;
+        }
+
+        let is_connected = peripheral.is_connected().await?;
+        let characteristics = discover_characteristics(&peripheral, is_connected)
+            .await
+            .map_err(|e| e.to_string());
+
+        scanned.push(ScannedPeripheral {
+            address: peripheral_address(&peripheral),
+            local_name,
+            rssi: properties.rssi,
+            is_connected,
+            services: properties.services,
+            characteristics,
+        });
+    }
+
+    Ok(scanned)
+}
+
+pub async fn connect_to(
+    name: &str,
+    service_uuid: Uuid,
+    on_state: &StateListener,
+) -> Result<Connection<impl Peripheral>> {
+    // All peripheral devices in range.
+    on_state(ConnectionState::Scanning);
+    let peripherals = scan(vec![service_uuid]).await?;
+    for peripheral in peripherals {
+        let local_name = local_name(&peripheral).await?;
+        tracing::debug!("Found peripheral {:?}.", &local_name);
+
+        // Check if it's the peripheral we want.
+        if local_name.contains(name) {
+            if let Some(connection) =
+                try_connect(peripheral, &local_name, service_uuid, on_state).await?
+            {
+                return Ok(connection);
             }
         }
     }
 
-    adapter.stop_scan().await?;
+    Err(Error::NotFound(name.to_string()))
+}
 
-    Err("No devices found".into())
+/// Connects to the exact peripheral previously found by [`discover`]. Peripherals already
+/// known to the OS are connected right away, the scan only happens for unknown ones.
+pub async fn connect_to_address(
+    address: &str,
+    service_uuid: Uuid,
+    on_state: &StateListener,
+) -> Result<Connection<impl Peripheral>> {
+    let adapter = select_adapter().await?;
+    let known_peripherals = adapter.peripherals().await?;
+    let known = known_peripherals
+        .into_iter()
+        .find(|peripheral| peripheral_address(peripheral).eq_ignore_ascii_case(address));
+
+    let peripheral = match known {
+        Some(peripheral) => Some(peripheral),
+        None => {
+            on_state(ConnectionState::Scanning);
+            let peripherals = scan(vec![service_uuid]).await?;
+            peripherals
+                .into_iter()
+                .find(|peripheral| peripheral_address(peripheral).eq_ignore_ascii_case(address))
+        }
+    };
+
+    let peripheral = peripheral.ok_or_else(|| Error::NotFound(address.to_string()))?;
+    let local_name = local_name(&peripheral).await?;
+
+    match try_connect(peripheral, &local_name, service_uuid, on_state).await? {
+        Some(connection) => Ok(connection),
+        None => Err(Error::ConnectionFailed(address.to_string())),
+    }
 }
@@ -0,0 +1,505 @@
+use crate::{
+    ble_actions::{BleAction, CalibrationReply},
+    calibration_log::{self, CalibrationEntry, Metric},
+    climate_data::{ClimateData, Timestamp},
+    config::{
+        self,
+        settings::{DeviceCalibration, Settings},
+        CALIBRATION_HUMIDITY_ADJUST, CALIBRATION_TEMPERATURE_ADJUST,
+    },
+    devices::Device,
+    error::{Error, Result},
+    history::History,
+};
+use std::{ops::RangeInclusive, time::Duration};
+use tokio::sync::oneshot;
+
+/// How long the sensor has to breathe fresh air before the calibration
+pub const CO2_SETTLE_TIME: Duration = Duration::from_secs(20 * 60);
+/// The readings of the last minutes have to be flat, a sensor still airing out drifts down
+pub const CO2_PLATEAU_WINDOW: Duration = Duration::from_secs(5 * 60);
+/// Well within the ±50 ppm accuracy of the MH-Z19
+const CO2_PLATEAU_MAX_STD_DEV: f64 = 15.0;
+/// The firmware measures every 2 seconds, so a window with fewer readings has gaps
+const CO2_PLATEAU_MIN_SAMPLES: usize = 60;
+/// The level the MH-Z19 takes the air it is calibrated in as
+const CO2_CALIBRATION_POINT: f32 = 400.0;
+/// Fresh air has around 420 ppm and the sensor takes the calibration point as 400 ppm
+pub const CO2_OUTDOOR_RANGE: RangeInclusive<f64> = 350.0..=500.0;
+
+/// CO2 readings over the [`CO2_PLATEAU_WINDOW`]
+#[derive(Debug, Clone, Copy)]
+pub struct Co2Plateau {
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+impl Co2Plateau {
+    pub fn is_stable(&self) -> bool {
+        self.std_dev <= CO2_PLATEAU_MAX_STD_DEV
+    }
+
+    pub fn is_outdoor(&self) -> bool {
+        CO2_OUTDOOR_RANGE.contains(&self.mean)
+    }
+
+    /// Tells the user the sensor is not in fresh air, `None` when it seems to be
+    pub fn outdoor_warning(&self) -> Option<String> {
+        (!self.is_outdoor()).then(|| {
+            format!(
+                "Around {:.0} ppm is far from fresh air (~420 ppm), is the sensor outside?",
+                self.mean
+            )
+        })
+    }
+}
+
+/// Whether the CO2 sensor is ready to be calibrated, a calibration in stale air shifts every
+/// later reading. Judged by the device readings, the calibration profile does not apply to
+/// what the sensor takes as the calibration point.
+#[derive(Debug, Clone, Copy)]
+pub struct Co2Readiness {
+    /// Time left of the [`CO2_SETTLE_TIME`]
+    pub remaining: Duration,
+    /// `None` until there are enough readings taken since the wizard started
+    pub plateau: Option<Co2Plateau>,
+}
+
+impl Co2Readiness {
+    /// Looks at the readings taken since the sensor was put into fresh air
+    pub fn of(history: &History, started_at: Timestamp) -> Self {
+        let now = Timestamp::default().as_f64();
+        let elapsed = Duration::from_millis((now - started_at.as_f64()).max(0.0) as u64);
+        let window_start = (now - CO2_PLATEAU_WINDOW.as_millis() as f64).max(started_at.as_f64());
+
+        let readings = history
+            .raw_co2_history
+            .iter()
+            .filter(|(timestamp, _)| timestamp.as_f64() >= window_start)
+            .map(|(_, co2)| *co2)
+            .collect::<Vec<_>>();
+
+        let plateau = (readings.len() >= CO2_PLATEAU_MIN_SAMPLES).then(|| {
+            let count = readings.len() as f64;
+            let mean = readings.iter().sum::<f64>() / count;
+            let variance = readings.iter().map(|co2| (co2 - mean).powi(2)).sum::<f64>() / count;
+
+            Co2Plateau {
+                mean,
+                std_dev: variance.sqrt(),
+            }
+        });
+
+        Self {
+            remaining: CO2_SETTLE_TIME.saturating_sub(elapsed),
+            plateau,
+        }
+    }
+
+    /// Settled and flat, a sensor that drifted reads a flat level far from fresh air as well
+    pub fn is_ready(&self) -> bool {
+        self.remaining.is_zero() && self.plateau.is_some_and(|plateau| plateau.is_stable())
+    }
+
+    /// Ready, but far from outdoor levels, only calibrated when the user confirms it
+    pub fn needs_confirmation(&self) -> bool {
+        self.is_ready() && self.plateau.is_some_and(|plateau| !plateau.is_outdoor())
+    }
+}
+
+/// Calibration of a reading corrected by an offset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adjustment {
+    pub is_calibrated: bool,
+    /// Offset the device adds, only known when it was written from this machine or it is
+    /// still the firmware default
+    pub adjust: Option<i32>,
+}
+
+/// What the device reports about its calibration, `None` for the calibrations its firmware
+/// does not have
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CalibrationState {
+    pub co2: Option<bool>,
+    pub temperature: Option<Adjustment>,
+    pub humidity: Option<Adjustment>,
+}
+
+impl CalibrationState {
+    /// Fills in the offsets the device does not report from the ones written before
+    pub fn with_adjusts(mut self, stored: Option<&DeviceCalibration>) -> Self {
+        for quantity in [Quantity::Temperature, Quantity::Humidity] {
+            if let Some(adjustment) = quantity.state_mut(&mut self) {
+                adjustment.adjust = if adjustment.is_calibrated {
+                    stored.and_then(|stored| quantity.stored(stored))
+                } else {
+                    Some(quantity.default_adjust())
+                };
+            }
+        }
+
+        self
+    }
+}
+
+/// A reading the firmware corrects by adding a whole number offset to it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quantity {
+    Temperature,
+    Humidity,
+}
+
+impl Quantity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Quantity::Temperature => "temperature",
+            Quantity::Humidity => "humidity",
+        }
+    }
+
+    /// Unit the device measures and adjusts in
+    pub fn unit(self) -> &'static str {
+        match self {
+            Quantity::Temperature => "°C",
+            Quantity::Humidity => "%",
+        }
+    }
+
+    /// Unit the readings are shown and the references typed in, the configured one
+    pub fn display_unit(self) -> &'static str {
+        match self {
+            Quantity::Temperature => config::units().temperature.symbol(),
+            Quantity::Humidity => self.unit(),
+        }
+    }
+
+    /// A value in the [`Self::unit`] in the [`Self::display_unit`]
+    pub fn to_display(self, value: f32) -> f32 {
+        match self {
+            Quantity::Temperature => config::units().temperature.convert(value),
+            Quantity::Humidity => value,
+        }
+    }
+
+    /// Range of the sensor, anything outside is a typo rather than a reference reading
+    fn range(self) -> RangeInclusive<f32> {
+        match self {
+            Quantity::Temperature => -40.0..=85.0,
+            Quantity::Humidity => 0.0..=100.0,
+        }
+    }
+
+    /// A reference typed in the [`Self::display_unit`] in the unit of the device, the reason
+    /// when it is outside of the sensor range
+    pub fn reference_from_display(self, reference: f32) -> Result<f32, String> {
+        let range = self.range();
+        let converted = match self {
+            Quantity::Temperature => config::units().temperature.to_celsius(reference),
+            Quantity::Humidity => reference,
+        };
+        if !range.contains(&converted) {
+            return Err(format!(
+                "The {} must be between {}{unit} and {}{unit}",
+                self.name(),
+                self.to_display(*range.start()).round(),
+                self.to_display(*range.end()).round(),
+                unit = self.display_unit()
+            ));
+        }
+
+        Ok(converted)
+    }
+
+    /// The latest reading of the device in the [`Self::display_unit`], the one a calibration
+    /// is computed from
+    pub fn displayed_reading(self, history: &History) -> Option<f32> {
+        history
+            .latest_raw
+            .and_then(|data| self.measured(&data))
+            .map(|value| self.to_display(value))
+    }
+
+    /// What the firmware adds until it is calibrated for the first time
+    fn default_adjust(self) -> i32 {
+        match self {
+            Quantity::Temperature => CALIBRATION_TEMPERATURE_ADJUST,
+            Quantity::Humidity => CALIBRATION_HUMIDITY_ADJUST as i32,
+        }
+    }
+
+    fn measured(self, data: &ClimateData) -> Option<f32> {
+        match self {
+            Quantity::Temperature => data.is_temperature_valid().then_some(data.temperature),
+            Quantity::Humidity => Some(data.humidity),
+        }
+    }
+
+    fn action(self, adjust: i32, reply: CalibrationReply) -> BleAction {
+        match self {
+            Quantity::Temperature => BleAction::CalibrateTemperature { adjust, reply },
+            Quantity::Humidity => BleAction::CalibrateHumidity { adjust, reply },
+        }
+    }
+
+    fn stored_adjust(self, calibration: &mut DeviceCalibration) -> &mut Option<i32> {
+        match self {
+            Quantity::Temperature => &mut calibration.temperature_adjust,
+            Quantity::Humidity => &mut calibration.humidity_adjust,
+        }
+    }
+
+    fn stored(self, calibration: &DeviceCalibration) -> Option<i32> {
+        match self {
+            Quantity::Temperature => calibration.temperature_adjust,
+            Quantity::Humidity => calibration.humidity_adjust,
+        }
+    }
+
+    fn state(self, state: &CalibrationState) -> Option<Adjustment> {
+        match self {
+            Quantity::Temperature => state.temperature,
+            Quantity::Humidity => state.humidity,
+        }
+    }
+
+    fn state_mut(self, state: &mut CalibrationState) -> &mut Option<Adjustment> {
+        match self {
+            Quantity::Temperature => &mut state.temperature,
+            Quantity::Humidity => &mut state.humidity,
+        }
+    }
+}
+
+/// Waits for the device to answer the calibration request
+async fn send_calibration(
+    device: &Device,
+    action: impl FnOnce(CalibrationReply) -> BleAction,
+) -> Result<()> {
+    let (reply, result) = oneshot::channel();
+    device.send(action(reply)).await;
+    result.await.map_err(|_| {
+        Error::CalibrationUnavailable(device.label.clone(), "not connected over BLE")
+    })?
+}
+
+/// Keeps the attempt in the calibration log, a failure to write it does not undo the
+/// calibration
+fn log_attempt(
+    device: &Device,
+    metric: Metric,
+    reference: f32,
+    measured: Option<f32>,
+    adjust: Option<i32>,
+    result: &Result<()>,
+) {
+    let entry = CalibrationEntry {
+        timestamp: Timestamp::default(),
+        device: device.id.clone(),
+        metric,
+        reference,
+        measured,
+        adjust,
+        error: result.as_ref().err().map(ToString::to_string),
+    };
+    if let Err(e) = calibration_log::append(&entry) {
+        tracing::error!("Failed to log the calibration of {}: {e}", device.label);
+    }
+}
+
+/// Makes the device take the current CO2 level as the fresh air baseline
+pub async fn calibrate_co2(device: &Device) -> Result<()> {
+    let measured = device
+        .history
+        .read()
+        .unwrap()
+        .latest_raw
+        .filter(ClimateData::is_co2_valid)
+        .and_then(|data| data.co2);
+
+    let result = send_calibration(device, |reply| BleAction::CalibrateCo2 { reply }).await;
+    log_attempt(
+        device,
+        Metric::Co2,
+        CO2_CALIBRATION_POINT,
+        measured.map(|co2| co2 as f32),
+        None,
+        &result,
+    );
+    result?;
+    tracing::info!("Calibrated CO2 of {}", device.label);
+    if let Some(state) = device.calibration.write().unwrap().as_mut() {
+        state.co2 = Some(true);
+    }
+
+    Ok(())
+}
+
+/// The firmware adds a whole number to the raw reading, so the new adjust is the current one
+/// corrected by the difference between the reference and the reported reading. The reading
+/// is the one the device sent, a host-side correction would be counted twice.
+fn adjust_for(reference: f32, measured: f32, current_adjust: i32) -> i32 {
+    current_adjust + (reference - measured).round() as i32
+}
+
+/// The offset the device adds now, `None` when it was calibrated from another machine. A new
+/// adjust computed from a guessed one would shift the readings instead of fixing them.
+fn known_adjust(device: &Device, quantity: Quantity, stored: Option<i32>) -> Option<i32> {
+    let reported = device
+        .calibration
+        .read()
+        .unwrap()
+        .as_ref()
+        .and_then(|state| quantity.state(state));
+
+    match reported {
+        Some(Adjustment {
+            is_calibrated: false,
+            ..
+        }) => Some(quantity.default_adjust()),
+        Some(Adjustment { adjust, .. }) => adjust,
+        // the device did not tell, only what was written from here is certain
+        None => stored,
+    }
+}
+
+/// Calibrates the quantity of the device against a reference instrument and returns the
+/// adjust written to the device. `current_adjust` is the offset the device adds now, only
+/// needed when it is not known from the device or the previous calibrations.
+pub async fn calibrate(
+    device: &Device,
+    quantity: Quantity,
+    reference: f32,
+    current_adjust: Option<i32>,
+) -> Result<i32> {
+    let measured = device
+        .history
+        .read()
+        .unwrap()
+        .latest_raw
+        .and_then(|data| quantity.measured(&data))
+        .ok_or_else(|| {
+            Error::CalibrationUnavailable(device.label.clone(), "no valid reading yet")
+        })?;
+
+    let mut settings = Settings::load()?;
+    let stored_adjust =
+        quantity.stored_adjust(settings.calibrations.entry(device.id.clone()).or_default());
+    let current_adjust = current_adjust
+        .or_else(|| known_adjust(device, quantity, *stored_adjust))
+        .ok_or_else(|| {
+            Error::CalibrationUnavailable(
+                device.label.clone(),
+                "it was calibrated from another machine, pass its current adjust with --current-adjust",
+            )
+        })?;
+    let adjust = adjust_for(reference, measured, current_adjust);
+
+    let result = send_calibration(device, |reply| quantity.action(adjust, reply)).await;
+    log_attempt(
+        device,
+        quantity.into(),
+        reference,
+        Some(measured),
+        Some(adjust),
+        &result,
+    );
+    result?;
+
+    tracing::info!(
+        "Calibrated {} of {}, adjust {adjust}",
+        quantity.name(),
+        device.label
+    );
+    *stored_adjust = Some(adjust);
+    settings.save()?;
+
+    if let Some(state) = device.calibration.write().unwrap().as_mut() {
+        *quantity.state_mut(state) = Some(Adjustment {
+            is_calibrated: true,
+            adjust: Some(adjust),
+        });
+    }
+
+    Ok(adjust)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A wizard started `elapsed` ago with a CO2 reading every 2 seconds since, like the
+    /// firmware sends them
+    fn wizard(elapsed: Duration, co2: impl Fn(usize) -> i32) -> (History, Timestamp) {
+        let now = Timestamp::default().as_f64();
+        let started_at = now - elapsed.as_millis() as f64;
+        let mut history = History::new();
+
+        for index in 0..(elapsed.as_secs() / 2) as usize {
+            let data: ClimateData = serde_json::from_value(json!({
+                "co2": co2(index), "temperature": 12.0, "eco2": 400, "etvoc": 0,
+                "pressure": 1013.0, "humidity": 60.0, "light": 1000.0,
+                "timestamp": started_at + (index * 2000) as f64,
+            }))
+            .unwrap();
+            history.capture_measurement(&data, &data);
+        }
+
+        (history, serde_json::from_value(json!(started_at)).unwrap())
+    }
+
+    const SETTLED: Duration = CO2_SETTLE_TIME.saturating_add(Duration::from_secs(60));
+
+    #[test]
+    fn too_few_readings_have_no_plateau() {
+        let (history, started_at) = wizard(Duration::from_secs(60), |_| 420);
+        let readiness = Co2Readiness::of(&history, started_at);
+
+        assert!(readiness.plateau.is_none());
+        assert!(!readiness.is_ready());
+    }
+
+    #[test]
+    fn noisy_readings_are_not_stable() {
+        let (history, started_at) = wizard(SETTLED, |index| 400 + (index % 2) as i32 * 60);
+        let readiness = Co2Readiness::of(&history, started_at);
+
+        let plateau = readiness.plateau.unwrap();
+        assert!(!plateau.is_stable());
+        assert!(readiness.remaining.is_zero());
+        assert!(!readiness.is_ready());
+    }
+
+    #[test]
+    fn flat_readings_wait_for_the_settle_time() {
+        let (history, started_at) = wizard(CO2_SETTLE_TIME / 2, |_| 420);
+        let readiness = Co2Readiness::of(&history, started_at);
+
+        assert!(readiness.plateau.unwrap().is_stable());
+        assert!(!readiness.remaining.is_zero());
+        assert!(!readiness.is_ready());
+    }
+
+    #[test]
+    fn flat_outdoor_readings_after_the_settle_time_are_ready() {
+        let (history, started_at) = wizard(SETTLED, |_| 420);
+        let readiness = Co2Readiness::of(&history, started_at);
+
+        let plateau = readiness.plateau.unwrap();
+        assert_eq!(plateau.mean, 420.0);
+        assert!(plateau.outdoor_warning().is_none());
+        assert!(readiness.is_ready());
+        assert!(!readiness.needs_confirmation());
+    }
+
+    #[test]
+    fn flat_readings_far_from_outdoor_levels_need_a_confirmation() {
+        // a drifted sensor reads a flat level above the outdoor range in fresh air
+        let (history, started_at) = wizard(SETTLED, |_| 620);
+        let readiness = Co2Readiness::of(&history, started_at);
+
+        assert!(readiness.plateau.unwrap().outdoor_warning().is_some());
+        assert!(readiness.is_ready());
+        assert!(readiness.needs_confirmation());
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{
+    calibration::Quantity,
+    climate_data::Timestamp,
+    error::{Error, Result},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+use strum::Display;
+
+const DAY_MILLIS: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Display)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    #[strum(to_string = "CO2")]
+    Co2,
+    #[strum(to_string = "temperature")]
+    Temperature,
+    #[strum(to_string = "humidity")]
+    Humidity,
+}
+
+impl Metric {
+    pub fn unit(self) -> &'static str {
+        match self {
+            Metric::Co2 => " ppm",
+            Metric::Temperature => Quantity::Temperature.unit(),
+            Metric::Humidity => Quantity::Humidity.unit(),
+        }
+    }
+}
+
+impl From<Quantity> for Metric {
+    fn from(quantity: Quantity) -> Self {
+        match quantity {
+            Quantity::Temperature => Metric::Temperature,
+            Quantity::Humidity => Metric::Humidity,
+        }
+    }
+}
+
+/// A calibration sent to a device, one JSON line of the log whatever the device answered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationEntry {
+    pub timestamp: Timestamp,
+    /// BLE address or `simulator`, the same as the calibrations in the config file
+    pub device: String,
+    pub metric: Metric,
+    /// Reading of the reference instrument, for CO2 the level the sensor takes fresh air as
+    pub reference: f32,
+    /// What the device reported right before the calibration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub measured: Option<f32>,
+    /// Offset written to the device
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adjust: Option<i32>,
+    /// Why the calibration failed, `None` when the device confirmed it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CalibrationEntry {
+    pub fn is_accepted(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Reference, reading and adjust, e.g. `reference 21.5°C, measured 22.8°C, adjust -1°C`
+    pub fn describe(&self) -> String {
+        let unit = self.metric.unit();
+        let mut parts = vec![format!("reference {}{unit}", self.reference)];
+        if let Some(measured) = self.measured {
+            parts.push(format!("measured {measured:.1}{unit}"));
+        }
+        if let Some(adjust) = self.adjust {
+            parts.push(format!("adjust {adjust:+}{unit}"));
+        }
+
+        parts.join(", ")
+    }
+
+    pub fn outcome(&self) -> String {
+        match &self.error {
+            None => "accepted".to_string(),
+            Some(error) => format!("failed: {error}"),
+        }
+    }
+
+    /// e.g. `today` or `12 days ago`, to tell when a re-calibration is due
+    pub fn age(&self) -> String {
+        let days = ((Timestamp::default().as_f64() - self.timestamp.as_f64()) / DAY_MILLIS) as u64;
+        match days {
+            0 => "today".to_string(),
+            1 => "yesterday".to_string(),
+            days => format!("{days} days ago"),
+        }
+    }
+}
+
+pub fn path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("co2nsole").join("calibrations.jsonl"))
+}
+
+pub fn append(entry: &CalibrationEntry) -> Result<()> {
+    let path =
+        path().ok_or_else(|| Error::Config("can not find the data directory".to_string()))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut line = serde_json::to_string(entry).map_err(std::io::Error::from)?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)?
+        .write_all(line.as_bytes())?;
+
+    Ok(())
+}
+
+/// All the entries from the oldest, a missing log means nothing was calibrated yet
+pub fn read() -> Result<Vec<CalibrationEntry>> {
+    let path = match path() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(Vec::new()),
+    };
+
+    let entries = fs::read_to_string(&path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            // a line cut by a crash must not hide the rest of the log
+            Err(e) => {
+                tracing::warn!("Skipping a bad line of {}: {e}", path.display());
+                None
+            }
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+/// Entries of the device from the newest
+pub fn read_device(device: &str) -> Result<Vec<CalibrationEntry>> {
+    let mut entries = read()?;
+    entries.retain(|entry| entry.device == device);
+    entries.reverse();
+
+    Ok(entries)
+}
@@ -1,9 +1,11 @@
-use crate::bluetooth::FromBleData;
+use crate::{
+    bluetooth::FromBleData,
+    error::{Error, Result},
+};
 use chrono::TimeZone;
-use serde::Deserialize;
-use std::error::Error;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
 #[repr(transparent)]
 pub struct Timestamp(f64);
 
@@ -27,7 +29,25 @@ impl Timestamp {
     }
 }
 
-#[derive(Debug, Clone, Copy, Deserialize)]
+/// Sensors the firmware failed to read, the bits of `encodeTO2Errors` in `core/src/serialize.cpp`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct SensorFaults(u16);
+
+bitflags::bitflags! {
+    impl SensorFaults: u16 {
+        /// CO2
+        const MHZ19 = 1 << 0;
+        /// Temperature and pressure
+        const BMP280 = 1 << 1;
+        /// Light
+        const BH1750 = 1 << 2;
+        /// eCO2 and TVOC
+        const CCS811 = 1 << 3;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
 pub struct ClimateData {
     pub co2: Option<i32>,
     pub temperature: f32,
@@ -36,12 +56,181 @@ pub struct ClimateData {
     pub pressure: f32,
     pub humidity: f32,
     pub light: Option<f32>,
+    /// Readings of a faulty sensor are stale or garbage
+    #[serde(default)]
+    pub error_flags: SensorFaults,
     #[serde(default)]
     pub timestamp: Timestamp,
 }
 
+impl ClimateData {
+    pub fn is_co2_valid(&self) -> bool {
+        !self.error_flags.contains(SensorFaults::MHZ19)
+    }
+
+    pub fn is_eco2_valid(&self) -> bool {
+        !self.error_flags.contains(SensorFaults::CCS811)
+    }
+
+    pub fn is_temperature_valid(&self) -> bool {
+        !self.error_flags.contains(SensorFaults::BMP280)
+    }
+
+    pub fn is_pressure_valid(&self) -> bool {
+        !self.error_flags.contains(SensorFaults::BMP280)
+    }
+
+    pub fn is_light_valid(&self) -> bool {
+        !self.error_flags.contains(SensorFaults::BH1750)
+    }
+}
+
+/// First byte of the compact binary payload, see `core/src/serialize.cpp` for the layout
+pub const FORMAT_BINARY_V1: u8 = 0x01;
+pub const BINARY_V1_SIZE: usize = 17;
+/// Marks a reading the sensor could not take
+const MISSING: u16 = 0xFFFF;
+
+impl ClimateData {
+    fn from_json(data: Vec<u8>) -> Result<Self> {
+        serde_json::from_slice(&data).map_err(|e| Error::Decode {
+            reason: e.to_string(),
+            payload: data,
+        })
+    }
+
+    fn from_binary_v1(data: Vec<u8>) -> Result<Self> {
+        if data.len() != BINARY_V1_SIZE {
+            return Err(Error::Decode {
+                reason: format!(
+                    "binary payload must be {BINARY_V1_SIZE} bytes, got {}",
+                    data.len()
+                ),
+                payload: data,
+            });
+        }
+
+        let field = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let optional = |value: u16| (value != MISSING).then_some(value);
+        let clamp = |value: u16| i16::try_from(value).unwrap_or(i16::MAX);
+
+        Ok(Self {
+            temperature: field(1) as i16 as f32 / 100.0,
+            pressure: field(3) as f32 / 10.0,
+            humidity: field(5) as f32 / 100.0,
+            light: optional(field(7)).map(f32::from),
+            co2: optional(field(9)).map(i32::from),
+            eco2: clamp(field(11)),
+            etvoc: clamp(field(13)),
+            error_flags: SensorFaults::from_bits_retain(field(15)),
+            timestamp: Timestamp::default(),
+        })
+    }
+}
+
 impl FromBleData for ClimateData {
-    fn from_bytes(data: Vec<u8>) -> Result<Self, Box<dyn Error>> {
-        Ok(serde_json::from_slice(&data)?)
+    const FRAGMENTED: bool = true;
+
+    /// Tells the formats apart by the first byte, JSON always starts with an object
+    fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        match data.first() {
+            Some(b'{') => Self::from_json(data),
+            Some(&FORMAT_BINARY_V1) => Self::from_binary_v1(data),
+            Some(format) => Err(Error::Decode {
+                reason: format!("unknown payload format {format:#04x}"),
+                payload: data,
+            }),
+            None => Err(Error::Decode {
+                reason: "empty payload".to_string(),
+                payload: data,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes the frame the way `encodeClimateData` in `core/src/serialize.cpp` does
+    fn binary_v1(fields: [u16; 8]) -> Vec<u8> {
+        let mut frame = vec![FORMAT_BINARY_V1];
+        for field in fields {
+            frame.extend_from_slice(&field.to_le_bytes());
+        }
+
+        frame
+    }
+
+    #[test]
+    fn binary_v1_decodes_every_field() {
+        let frame = binary_v1([2345, 10132, 4130, 120, 612, 450, 17, 0]);
+        assert_eq!(frame.len(), BINARY_V1_SIZE);
+
+        let data = ClimateData::from_bytes(frame).unwrap();
+        assert_eq!(data.temperature, 23.45);
+        assert_eq!(data.pressure, 1013.2);
+        assert_eq!(data.humidity, 41.3);
+        assert_eq!(data.light, Some(120.0));
+        assert_eq!(data.co2, Some(612));
+        assert_eq!(data.eco2, 450);
+        assert_eq!(data.etvoc, 17);
+        assert!(data.error_flags.is_empty());
+    }
+
+    #[test]
+    fn binary_v1_negative_temperature() {
+        let frame = binary_v1([-1250i16 as u16, 9950, 8000, 0, 400, 400, 0, 0]);
+
+        let data = ClimateData::from_bytes(frame).unwrap();
+        assert_eq!(data.temperature, -12.5);
+        assert_eq!(data.pressure, 995.0);
+        assert_eq!(data.humidity, 80.0);
+    }
+
+    #[test]
+    fn binary_v1_missing_readings_and_flags() {
+        let flags = (SensorFaults::MHZ19 | SensorFaults::BH1750).bits();
+        let frame = binary_v1([2000, 10000, 5000, MISSING, MISSING, 40000, 0, flags]);
+
+        let data = ClimateData::from_bytes(frame).unwrap();
+        assert_eq!(data.light, None);
+        assert_eq!(data.co2, None);
+        // does not fit the i16 the JSON payload uses, so it saturates
+        assert_eq!(data.eco2, i16::MAX);
+        assert_eq!(data.error_flags, SensorFaults::MHZ19 | SensorFaults::BH1750);
+        assert!(!data.is_co2_valid());
+        assert!(!data.is_light_valid());
+        assert!(data.is_temperature_valid());
+    }
+
+    #[test]
+    fn binary_v1_short_input() {
+        let mut frame = binary_v1([2000, 10000, 5000, 0, 400, 400, 0, 0]);
+        frame.truncate(BINARY_V1_SIZE - 1);
+
+        assert!(matches!(
+            ClimateData::from_bytes(frame),
+            Err(Error::Decode { .. })
+        ));
+        assert!(matches!(
+            ClimateData::from_bytes(vec![FORMAT_BINARY_V1]),
+            Err(Error::Decode { .. })
+        ));
+        assert!(matches!(
+            ClimateData::from_bytes(Vec::new()),
+            Err(Error::Decode { .. })
+        ));
+    }
+
+    #[test]
+    fn unknown_format_version() {
+        let mut frame = binary_v1([2000, 10000, 5000, 0, 400, 400, 0, 0]);
+        frame[0] = 0x02;
+
+        assert!(matches!(
+            ClimateData::from_bytes(frame),
+            Err(Error::Decode { reason, .. }) if reason.contains("0x02")
+        ));
     }
 }
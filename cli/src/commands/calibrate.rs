@@ -0,0 +1,173 @@
+use super::{first_measurement, single_device, stream};
+use crate::{
+    args::CalibrateTarget,
+    calibration::{self, Co2Readiness, Quantity},
+    calibration_log,
+    climate_data::Timestamp,
+    config::settings::Settings,
+    devices::Device,
+    error::{Error, Result},
+    sources::SourceKind,
+};
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+use tokio::time::interval;
+
+/// A BLE connection takes a while, especially right after the device was powered on
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(60);
+/// How often the CO2 calibration reports how far the sensor is from settled
+const CO2_PROGRESS_INTERVAL: Duration = Duration::from_secs(30);
+/// The readings settle soon after the settle time in fresh air, not within this long means
+/// the sensor is not outside
+const CO2_WAIT_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+pub fn print_calibration_log(settings: &Settings) -> Result<()> {
+    let entries = calibration_log::read()?;
+    if entries.is_empty() {
+        println!("Nothing was calibrated from this machine yet");
+    }
+
+    for entry in entries {
+        let device = match settings.alias_of(&entry.device) {
+            Some(alias) => format!("{alias} ({})", entry.device),
+            None => entry.device.clone(),
+        };
+        println!(
+            "{}  {device}  {}: {}  {}",
+            entry.timestamp.format("%Y-%m-%d %H:%M").unwrap_or_default(),
+            entry.metric,
+            entry.describe(),
+            entry.outcome()
+        );
+    }
+
+    Ok(())
+}
+
+/// Waits for the CO2 readings to settle in fresh air unless `now`, then calibrates
+async fn calibrate_co2(device: &Device, now: bool) -> Result<()> {
+    if !now {
+        println!(
+            "Keep {} in fresh air until the readings settle",
+            device.label
+        );
+        let started_at = Timestamp::default();
+        let waiting_since = Instant::now();
+        let mut progress = interval(CO2_PROGRESS_INTERVAL);
+
+        loop {
+            progress.tick().await;
+            if waiting_since.elapsed() > CO2_WAIT_TIMEOUT {
+                return Err(Error::CalibrationUnavailable(
+                    device.label.clone(),
+                    "the CO2 readings did not settle in fresh air within an hour",
+                ));
+            }
+
+            let readiness = Co2Readiness::of(&device.history.read().unwrap(), started_at);
+            if readiness.is_ready() && !readiness.needs_confirmation() {
+                break;
+            }
+
+            let remaining = readiness.remaining.as_secs();
+            match readiness.plateau {
+                Some(plateau) => println!(
+                    "{:02}:{:02} left, CO2 {:.0} ppm ±{:.0} ppm",
+                    remaining / 60,
+                    remaining % 60,
+                    plateau.mean,
+                    plateau.std_dev
+                ),
+                None => println!(
+                    "{:02}:{:02} left, waiting for more readings",
+                    remaining / 60,
+                    remaining % 60
+                ),
+            }
+            if let Some(warning) = readiness
+                .plateau
+                .and_then(|plateau| plateau.outdoor_warning())
+            {
+                println!("{warning}");
+            }
+            if readiness.needs_confirmation() {
+                return Err(Error::CalibrationUnavailable(
+                    device.label.clone(),
+                    "the CO2 readings settled far from fresh air, pass --now to calibrate anyway",
+                ));
+            }
+        }
+    }
+
+    calibration::calibrate_co2(device).await?;
+    println!(
+        "Calibrated, {} takes the current CO2 level as 400 ppm",
+        device.label
+    );
+
+    Ok(())
+}
+
+/// Calibrates the device the same way the calibration popup does, refuses to pick one of
+/// several devices in range
+pub async fn calibrate(
+    kind: SourceKind,
+    settings: Settings,
+    record: Option<&Path>,
+    target: &CalibrateTarget,
+) -> Result<()> {
+    // a typo in the reference is reported before waiting for the device
+    let reference = match *target {
+        CalibrateTarget::Temperature {
+            reference,
+            current_adjust,
+        } => Some((Quantity::Temperature, reference, current_adjust)),
+        CalibrateTarget::Humidity {
+            reference,
+            current_adjust,
+        } => Some((Quantity::Humidity, reference, current_adjust)),
+        CalibrateTarget::Co2 { .. } | CalibrateTarget::Log => None,
+    };
+    let reference = reference
+        .map(|(quantity, reference, current_adjust)| {
+            quantity
+                .reference_from_display(reference)
+                .map(|reference| (quantity, reference, current_adjust))
+        })
+        .transpose()
+        .map_err(Error::InvalidReference)?;
+
+    let kind = single_device(kind, &settings, CONNECT_TIMEOUT).await?;
+    let mut measurements = stream(kind, settings, record)?;
+    let device = first_measurement(&mut measurements, CONNECT_TIMEOUT)
+        .await?
+        .device;
+
+    let Some((quantity, reference, current_adjust)) = reference else {
+        return match *target {
+            CalibrateTarget::Co2 { now } => calibrate_co2(&device, now).await,
+            // listed without connecting to anything
+            _ => Ok(()),
+        };
+    };
+
+    if let Some(measured) = quantity.displayed_reading(&device.history.read().unwrap()) {
+        println!(
+            "{} reads {measured:.1}{unit}, the reference {:.1}{unit}",
+            device.label,
+            quantity.to_display(reference),
+            unit = quantity.display_unit()
+        );
+    }
+    let adjust = calibration::calibrate(&device, quantity, reference, current_adjust).await?;
+    println!(
+        "Calibrated, {} now adjusts its {} by {adjust:+}{}",
+        device.label,
+        quantity.name(),
+        quantity.unit()
+    );
+
+    Ok(())
+}
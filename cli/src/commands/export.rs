@@ -0,0 +1,128 @@
+use super::{stream, Measurement};
+use crate::{
+    args::ExportFormat, climate_data::ClimateData, config::settings::Settings, error::Result,
+    recorder::Recorder, sources::SourceKind,
+};
+use std::{
+    fs::OpenOptions,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::time::{sleep_until, Instant};
+
+const CSV_HEADER: &str = "timestamp,device,co2,temperature,humidity,pressure,light,eco2,etvoc,error_flags,raw_co2,raw_temperature,raw_humidity,raw_pressure,raw_light";
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// One row in the order of [`CSV_HEADER`], the raw columns are empty unless a calibration
+/// profile corrected the measurement
+fn csv_row(measurement: &Measurement) -> String {
+    let data = &measurement.data;
+    let raw = |value: fn(&ClimateData) -> Option<String>| {
+        measurement.raw.as_ref().and_then(value).unwrap_or_default()
+    };
+
+    [
+        data.timestamp
+            .format("%Y-%m-%dT%H:%M:%S%.3f%z")
+            .unwrap_or_default(),
+        measurement.device.id.clone(),
+        optional(data.co2),
+        data.temperature.to_string(),
+        data.humidity.to_string(),
+        data.pressure.to_string(),
+        optional(data.light),
+        data.eco2.to_string(),
+        data.etvoc.to_string(),
+        data.error_flags.bits().to_string(),
+        raw(|raw| raw.co2.map(|co2| co2.to_string())),
+        raw(|raw| Some(raw.temperature.to_string())),
+        raw(|raw| Some(raw.humidity.to_string())),
+        raw(|raw| Some(raw.pressure.to_string())),
+        raw(|raw| raw.light.map(|light| light.to_string())),
+    ]
+    .join(",")
+}
+
+/// Appends to the file so a cron job can keep adding to the same export, the CSV header is
+/// only written into an empty file
+fn open_csv(output: Option<&PathBuf>) -> Result<Box<dyn Write>> {
+    let mut writer: Box<dyn Write> = match output {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            let is_empty = file.metadata()?.len() == 0;
+            let mut writer = BufWriter::new(file);
+            if is_empty {
+                writeln!(writer, "{CSV_HEADER}")?;
+            }
+            Box::new(writer)
+        }
+        None => {
+            let mut writer = io::stdout();
+            writeln!(writer, "{CSV_HEADER}")?;
+            Box::new(writer)
+        }
+    };
+    writer.flush()?;
+
+    Ok(writer)
+}
+
+/// Writes the measurements of every device until the count or the duration is reached, or
+/// the process is interrupted when neither is given
+pub async fn export(
+    kind: SourceKind,
+    settings: Settings,
+    record: Option<&Path>,
+    format: ExportFormat,
+    output: Option<&PathBuf>,
+    count: Option<usize>,
+    duration: Option<Duration>,
+) -> Result<()> {
+    let mut measurements = stream(kind, settings, record)?;
+    let (mut csv, mut json) = match format {
+        ExportFormat::Csv => (Some(open_csv(output)?), None),
+        ExportFormat::Json => (
+            None,
+            Some(match output {
+                Some(path) => Recorder::open(path)?,
+                None => Recorder::stdout(),
+            }),
+        ),
+    };
+
+    // a year is as good as forever and keeps the select below simple
+    let deadline = Instant::now() + duration.unwrap_or(Duration::from_secs(365 * 24 * 60 * 60));
+    let mut exported = 0;
+
+    while count.is_none_or(|count| exported < count) {
+        let measurement = tokio::select! {
+            measurement = measurements.recv() => match measurement {
+                Some(measurement) => measurement,
+                None => break,
+            },
+            _ = sleep_until(deadline) => break,
+        };
+
+        if let Some(csv) = &mut csv {
+            writeln!(csv, "{}", csv_row(&measurement))?;
+            // flushed every row, the export may well be interrupted
+            csv.flush()?;
+        }
+        if let Some(json) = &mut json {
+            json.record(
+                &measurement.device.id,
+                &measurement.data,
+                measurement.raw.as_ref(),
+            )?;
+        }
+        exported += 1;
+    }
+
+    tracing::info!("Exported {exported} measurements");
+
+    Ok(())
+}
@@ -0,0 +1,229 @@
+mod calibrate;
+mod export;
+mod status;
+mod tui;
+mod watch;
+
+pub use calibrate::{calibrate, print_calibration_log};
+pub use export::export;
+pub use status::status;
+pub use tui::tui;
+pub use watch::{daemon, watch};
+
+use crate::{
+    bluetooth,
+    climate_data::ClimateData,
+    config::{self, settings::Settings},
+    devices::{self, Device, Devices, OnData},
+    error::{Error, Result},
+    reactions,
+    recorder::Recorder,
+    sources::SourceKind,
+};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver},
+    time::{sleep, timeout},
+};
+
+/// Pause between the scans looking for the device of a command
+const DISCOVERY_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// A measurement of one of the devices as the commands without the dashboard consume it
+pub struct Measurement {
+    pub device: Arc<Device>,
+    /// Corrected by the calibration profile of the device
+    pub data: ClimateData,
+    /// What the device sent, only when the calibration profile changed it
+    pub raw: Option<ClimateData>,
+}
+
+/// Connects to the devices of the source in the background. Every measurement is corrected by
+/// the calibration profile, recorded and captured into the history of its device before it
+/// is handed to `on_measurement`.
+pub fn start(
+    kind: SourceKind,
+    settings: Settings,
+    devices: Arc<Devices>,
+    record: Option<&Path>,
+    on_measurement: impl Fn(&Device, &ClimateData, Option<&ClimateData>) + Send + Sync + 'static,
+) -> Result<()> {
+    let recorder = record.map(Recorder::open).transpose()?.map(Mutex::new);
+
+    let on_data: OnData = {
+        let devices = Arc::clone(&devices);
+
+        Arc::new(move |device: &Device, raw: ClimateData| {
+            tracing::debug!("New climate data from {}: {:?}", device.label, raw);
+            let data = device.profile.apply(&raw);
+            let original = (!device.profile.is_empty()).then_some(&raw);
+
+            if let Some(recorder) = &recorder {
+                if let Err(e) = recorder.lock().unwrap().record(&device.id, &data, original) {
+                    tracing::error!("Failed to record climate data: {:?}", e);
+                }
+            }
+
+            {
+                device
+                    .history
+                    .write()
+                    .unwrap()
+                    .capture_measurement(&data, &raw);
+            }
+
+            on_measurement(device, &data, original);
+
+            if config::reactions().enabled {
+                reactions::run_reactions(device.history.read().unwrap().flat.as_slice(), &devices);
+            }
+        })
+    };
+
+    tokio::spawn(devices::supervise(
+        kind,
+        Arc::new(settings),
+        Arc::clone(&devices),
+        on_data,
+    ));
+
+    Ok(())
+}
+
+/// Like [`start`] but hands the measurements over to the caller in the order they arrive
+pub fn stream(
+    kind: SourceKind,
+    settings: Settings,
+    record: Option<&Path>,
+) -> Result<UnboundedReceiver<Measurement>> {
+    let devices = Arc::new(Devices::new());
+    let (sender, receiver) = mpsc::unbounded_channel();
+
+    start(kind, settings, Arc::clone(&devices), record, {
+        move |device, data, raw| {
+            // the device is registered before it connects, so it is always found
+            if let Some(device) = devices.find(&device.id) {
+                let _ = sender.send(Measurement {
+                    device,
+                    data: *data,
+                    raw: raw.copied(),
+                });
+            }
+        }
+    })?;
+
+    Ok(receiver)
+}
+
+/// The source of the one device a command acts on. Without a named device it has to be the
+/// only co2nsole in range, whichever reports first could be the wrong unit to calibrate.
+async fn single_device(
+    kind: SourceKind,
+    settings: &Settings,
+    wait: Duration,
+) -> Result<SourceKind> {
+    if !matches!(kind, SourceKind::Ble) {
+        return Ok(kind);
+    }
+
+    let ble = config::ble();
+    let discovering = async {
+        loop {
+            let discovered = bluetooth::discover(&ble.main_local_name, ble.main_service).await?;
+            if !discovered.is_empty() {
+                return Ok::<_, Error>(discovered);
+            }
+            sleep(DISCOVERY_RETRY_DELAY).await;
+        }
+    };
+    let discovered = timeout(wait, discovering)
+        .await
+        .map_err(|_| Error::NotFound(ble.main_local_name.clone()))??;
+
+    match discovered.as_slice() {
+        [peripheral] => Ok(SourceKind::BleDevice {
+            address: peripheral.address.clone(),
+        }),
+        several => Err(Error::SeveralDevices(
+            several
+                .iter()
+                .map(|peripheral| match settings.alias_of(&peripheral.address) {
+                    Some(alias) => format!("{alias} ({})", peripheral.address),
+                    None => format!("{} {}", peripheral.local_name, peripheral.address),
+                })
+                .collect(),
+        )),
+    }
+}
+
+/// The first measurement of any device, fails when none arrives in time
+async fn first_measurement(
+    measurements: &mut UnboundedReceiver<Measurement>,
+    wait: Duration,
+) -> Result<Measurement> {
+    timeout(wait, measurements.recv())
+        .await
+        .ok()
+        .flatten()
+        .ok_or(Error::NoData(wait))
+}
+
+/// Name and value of every reading in the preferred units, `n/a` for a faulty sensor
+fn readings(data: &ClimateData) -> Vec<(&'static str, String)> {
+    let units = config::units();
+    let valid = |is_valid: bool, value: String| {
+        if is_valid {
+            value
+        } else {
+            "n/a".to_string()
+        }
+    };
+
+    vec![
+        (
+            "CO2",
+            valid(
+                data.is_co2_valid(),
+                data.co2
+                    .map(|co2| format!("{co2} ppm"))
+                    .unwrap_or("n/a".to_string()),
+            ),
+        ),
+        (
+            "temperature",
+            valid(
+                data.is_temperature_valid(),
+                format!(
+                    "{:.1}{}",
+                    units.temperature.convert(data.temperature),
+                    units.temperature.symbol()
+                ),
+            ),
+        ),
+        ("humidity", format!("{:.1}%", data.humidity)),
+        (
+            "pressure",
+            valid(
+                data.is_pressure_valid(),
+                format!(
+                    "{:.2} {}",
+                    units.pressure().convert(data.pressure),
+                    units.pressure().symbol()
+                ),
+            ),
+        ),
+        (
+            "light",
+            valid(
+                data.is_light_valid(),
+                data.light
+                    .map(|light| format!("{light:.0} lux"))
+                    .unwrap_or("n/a".to_string()),
+            ),
+        ),
+    ]
+}
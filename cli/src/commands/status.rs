@@ -0,0 +1,46 @@
+use super::{first_measurement, readings, single_device, stream};
+use crate::{config::settings::Settings, error::Result, recorder::Recorder, sources::SourceKind};
+use std::{path::Path, time::Duration};
+
+/// Prints the first measurement of the device, the exit code tells whether it is reachable.
+/// Fails with the devices in range when there are several and none of them was named.
+pub async fn status(
+    kind: SourceKind,
+    settings: Settings,
+    record: Option<&Path>,
+    json: bool,
+    timeout: Duration,
+) -> Result<()> {
+    let kind = single_device(kind, &settings, timeout).await?;
+    let mut measurements = stream(kind, settings, record)?;
+    let measurement = first_measurement(&mut measurements, timeout).await?;
+    let device = &measurement.device;
+
+    if json {
+        return Ok(Recorder::stdout().record(
+            &device.id,
+            &measurement.data,
+            measurement.raw.as_ref(),
+        )?);
+    }
+
+    println!("{} ({})", device.label, device.id);
+    for (name, value) in readings(&measurement.data) {
+        println!("  {name:<12} {value}");
+    }
+    if let Some(level) = device.battery.read().unwrap().latest() {
+        println!("  {:<12} {level}%", "battery");
+    }
+
+    let faults = measurement.data.error_flags;
+    if !faults.is_empty() {
+        let names = faults
+            .iter_names()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!("  sensor fault: {names}");
+    }
+
+    Ok(())
+}
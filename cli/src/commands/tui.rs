@@ -0,0 +1,82 @@
+use super::start;
+use crate::{
+    config::{self, settings::Settings},
+    devices::Devices,
+    error::Result,
+    sources::SourceKind,
+    tui_app::TerminalUi,
+};
+use crossterm::{
+    terminal::{disable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    ExecutableCommand,
+};
+use ratatui::{backend::CrosstermBackend, Terminal};
+use spinners::{Spinner, Spinners};
+use std::{
+    fmt::Display,
+    io::stdout,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::Notify;
+
+fn set_terminal_tab_title(climate_data: impl AsRef<str> + Display) {
+    use std::io::Write;
+
+    print!("\x1B]0;{}\x07", climate_data);
+    if let Err(e) = std::io::stdout().flush() {
+        tracing::error!("Failed to update title of the console: {:?}", e);
+    }
+}
+
+/// Shows the dashboard until the user exits it
+pub async fn tui(kind: SourceKind, settings: Settings, record: Option<&Path>) -> Result<()> {
+    let backend = CrosstermBackend::new(stdout());
+    let devices = Arc::new(Devices::new());
+    let terminal = Arc::new(Mutex::new(Terminal::new(backend)?));
+    let app = Arc::new(TerminalUi::new(Arc::clone(&devices))?);
+    let first_data = Arc::new(Notify::new());
+
+    let mut spinner = Spinner::new(Spinners::Pong, "Connecting to sensor".to_owned());
+    set_terminal_tab_title("Connecting to a sensor...");
+    start(kind, settings, Arc::clone(&devices), record, {
+        let devices = Arc::clone(&devices);
+        let terminal = Arc::clone(&terminal);
+        let app = Arc::clone(&app);
+        let first_data = Arc::clone(&first_data);
+
+        move |device, data, _| {
+            if devices.is_selected(device) {
+                let temperature = config::units().temperature;
+                set_terminal_tab_title(format!(
+                    "T {:.2}{}; CO2 {} ppm; H {}%",
+                    temperature.convert(data.temperature),
+                    temperature.symbol(),
+                    data.co2.unwrap_or(400),
+                    data.humidity.round()
+                ));
+            }
+
+            first_data.notify_one();
+            app.draw(&mut terminal.lock().unwrap());
+        }
+    })?;
+
+    first_data.notified().await;
+    spinner.stop();
+
+    stdout().execute(EnterAlternateScreen)?;
+    crossterm::terminal::enable_raw_mode()?;
+    terminal.lock().unwrap().clear()?;
+
+    // Exit of the app can happen only from the event poller:
+    let event_polling = TerminalUi::start_event_polling(Arc::clone(&app), terminal);
+    if let Err(e) = event_polling.await? {
+        tracing::error!("Terminal event polling failed: {:?}", e);
+    }
+
+    stdout().execute(LeaveAlternateScreen)?;
+    disable_raw_mode()?;
+
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use super::{readings, stream};
+use crate::{config::settings::Settings, error::Result, recorder::Recorder, sources::SourceKind};
+use std::path::Path;
+
+/// Prints every measurement of every device until the process is interrupted
+pub async fn watch(
+    kind: SourceKind,
+    settings: Settings,
+    record: Option<&Path>,
+    json: bool,
+) -> Result<()> {
+    let mut measurements = stream(kind, settings, record)?;
+    let mut json_output = json.then(Recorder::stdout);
+
+    while let Some(measurement) = measurements.recv().await {
+        match &mut json_output {
+            Some(output) => output.record(
+                &measurement.device.id,
+                &measurement.data,
+                measurement.raw.as_ref(),
+            )?,
+            None => {
+                let readings = readings(&measurement.data)
+                    .into_iter()
+                    .map(|(name, value)| format!("{name} {value}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                println!(
+                    "{} {}: {readings}",
+                    measurement
+                        .data
+                        .timestamp
+                        .format("%Y-%m-%d %H:%M:%S")
+                        .unwrap_or_default(),
+                    measurement.device.label
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Keeps the devices connected for the reactions, battery alerts and the recording only
+pub async fn daemon(kind: SourceKind, settings: Settings, record: Option<&Path>) -> Result<()> {
+    let mut measurements = stream(kind, settings, record)?;
+    tracing::info!("Running without the dashboard");
+
+    // everything happens on the way into the channel, nothing left to do with the data
+    while measurements.recv().await.is_some() {}
+
+    Ok(())
+}
@@ -7,11 +7,13 @@ pub const BLE_MAIN_SENSOR_CO2_CALIBRATION_CHAR: &[u8; 37] =
     b"beb5483e-36e1-4688-b7f5-ea07361b26a8\0";
 pub const BLE_MAIN_SENSOR_TEMP_CALIBRATION_CHAR: &[u8; 37] =
     b"d753f24d-3aa0-4678-b039-a52d3b2e3946\0";
+pub const BLE_MAIN_SENSOR_HUMIDITY_CALIBRATION_CHAR: &[u8; 37] =
+    b"c71d016d-31a8-4fbc-b47b-ad3f05796210\0";
 pub const BLE_MAIN_SENSOR_CALIBRATE_CO2: &[u8; 15] = b"CalibrateMhZ19\0";
 pub const BLE_MAIN_SENSOR_CALIBRATE_TEMPERATURE: &[u8; 21] = b"CalibrateTemperature\0";
-pub const BLE_MAIN_SENSOR_CALIBRATE_HUMIDITY: &[u8; 18] = b"CalibrateHumidity\0";
 pub const BLE_WINDOW_SERVICE_LOCAL_NAME: &[u8; 16] = b"co2nsole window\0";
 pub const BLE_WINDOW_SENSOR_SERVICE: &[u8; 37] = b"19B10000-E8F2-537E-4F6C-D104768A1214\0";
 pub const BLE_WINDOW_SENSOR_READ_CHAR: &[u8; 37] = b"a3b27688-3b9e-4214-970c-3db5f14c5d2b\0";
 pub const CALIBRATION_CO2_DEFAULT: u32 = 400;
 pub const CALIBRATION_TEMPERATURE_ADJUST: i32 = -8;
+pub const CALIBRATION_HUMIDITY_ADJUST: u32 = 5;
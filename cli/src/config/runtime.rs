@@ -0,0 +1,314 @@
+use super::{raw_bindings, safe_c_str_to_string, settings::Settings};
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::{str::FromStr, sync::OnceLock};
+use uuid::Uuid;
+
+fn binding(name: &str, c_str: &'static [u8]) -> String {
+    safe_c_str_to_string(c_str)
+        .unwrap_or_else(|e| panic!("Invalid {name} in conf.h: {e}"))
+        .to_string()
+}
+
+fn binding_uuid(name: &str, c_str: &'static [u8]) -> Uuid {
+    Uuid::from_str(&binding(name, c_str))
+        .unwrap_or_else(|e| panic!("Invalid {name} in conf.h: {e}"))
+}
+
+/// Names and UUIDs the firmware advertises, by default the ones of `shared/conf.h` the CLI
+/// was built with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BleConfig {
+    pub main_local_name: String,
+    pub main_service: Uuid,
+    pub stream_char: Uuid,
+    pub co2_calibration_char: Uuid,
+    pub temperature_calibration_char: Uuid,
+    pub humidity_calibration_char: Uuid,
+    /// Written to the CO2 calibration characteristic to start the calibration
+    pub calibrate_co2_command: String,
+    pub window_local_name: String,
+    pub window_service: Uuid,
+    pub window_read_char: Uuid,
+}
+
+impl Default for BleConfig {
+    fn default() -> Self {
+        Self {
+            main_local_name: binding(
+                "BLE_MAIN_SERVICE_LOCAL_NAME",
+                raw_bindings::BLE_MAIN_SERVICE_LOCAL_NAME,
+            ),
+            main_service: binding_uuid(
+                "BLE_MAIN_SENSOR_SERVICE",
+                raw_bindings::BLE_MAIN_SENSOR_SERVICE,
+            ),
+            stream_char: binding_uuid(
+                "BLE_MAIN_SENSOR_STREAM_CHAR",
+                raw_bindings::BLE_MAIN_SENSOR_STREAM_CHAR,
+            ),
+            co2_calibration_char: binding_uuid(
+                "BLE_MAIN_SENSOR_CO2_CALIBRATION_CHAR",
+                raw_bindings::BLE_MAIN_SENSOR_CO2_CALIBRATION_CHAR,
+            ),
+            temperature_calibration_char: binding_uuid(
+                "BLE_MAIN_SENSOR_TEMP_CALIBRATION_CHAR",
+                raw_bindings::BLE_MAIN_SENSOR_TEMP_CALIBRATION_CHAR,
+            ),
+            humidity_calibration_char: binding_uuid(
+                "BLE_MAIN_SENSOR_HUMIDITY_CALIBRATION_CHAR",
+                raw_bindings::BLE_MAIN_SENSOR_HUMIDITY_CALIBRATION_CHAR,
+            ),
+            calibrate_co2_command: binding(
+                "BLE_MAIN_SENSOR_CALIBRATE_CO2",
+                raw_bindings::BLE_MAIN_SENSOR_CALIBRATE_CO2,
+            ),
+            window_local_name: binding(
+                "BLE_WINDOW_SERVICE_LOCAL_NAME",
+                raw_bindings::BLE_WINDOW_SERVICE_LOCAL_NAME,
+            ),
+            window_service: binding_uuid(
+                "BLE_WINDOW_SENSOR_SERVICE",
+                raw_bindings::BLE_WINDOW_SENSOR_SERVICE,
+            ),
+            window_read_char: binding_uuid(
+                "BLE_WINDOW_SENSOR_READ_CHAR",
+                raw_bindings::BLE_WINDOW_SENSOR_READ_CHAR,
+            ),
+        }
+    }
+}
+
+/// Levels the dashboard and the advice judge the readings by
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Thresholds {
+    /// CO2 in ppm from which the air gets stuffy
+    pub co2_warning: i32,
+    /// CO2 in ppm from which it is time to open a window
+    pub co2_alert: i32,
+    /// Comfortable temperature in °C
+    pub temperature_min: f32,
+    pub temperature_max: f32,
+    /// Comfortable relative humidity in %
+    pub humidity_min: f32,
+    pub humidity_max: f32,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            co2_warning: 800,
+            co2_alert: 1000,
+            temperature_min: 20.0,
+            temperature_max: 25.0,
+            humidity_min: 30.0,
+            humidity_max: 70.0,
+        }
+    }
+}
+
+impl Thresholds {
+    fn validate(&self) -> Result<()> {
+        let ranges = [
+            (
+                "co2_warning",
+                "co2_alert",
+                self.co2_warning as f32,
+                self.co2_alert as f32,
+            ),
+            (
+                "temperature_min",
+                "temperature_max",
+                self.temperature_min,
+                self.temperature_max,
+            ),
+            (
+                "humidity_min",
+                "humidity_max",
+                self.humidity_min,
+                self.humidity_max,
+            ),
+        ];
+
+        match ranges.iter().find(|(_, _, low, high)| low >= high) {
+            Some((low, high, ..)) => Err(Error::Config(format!(
+                "thresholds: {low} must be lower than {high}"
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    pub fn convert(self, celsius: f32) -> f32 {
+        match self {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    /// Back from this unit into the °C the device measures in
+    pub fn to_celsius(self, value: f32) -> f32 {
+        match self {
+            TemperatureUnit::Celsius => value,
+            TemperatureUnit::Fahrenheit => (value - 32.0) * 5.0 / 9.0,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+        }
+    }
+
+    /// Displayed next to the preferred one
+    pub fn other(self) -> Self {
+        match self {
+            TemperatureUnit::Celsius => TemperatureUnit::Fahrenheit,
+            TemperatureUnit::Fahrenheit => TemperatureUnit::Celsius,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PressureUnit {
+    #[default]
+    MmHg,
+    Hpa,
+}
+
+impl PressureUnit {
+    pub fn convert(self, hpa: f32) -> f32 {
+        match self {
+            PressureUnit::MmHg => hpa * 0.750_063_8,
+            PressureUnit::Hpa => hpa,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            PressureUnit::MmHg => "mm Hg",
+            PressureUnit::Hpa => "hPa",
+        }
+    }
+
+    /// Displayed next to the preferred one
+    pub fn other(self) -> Self {
+        match self {
+            PressureUnit::MmHg => PressureUnit::Hpa,
+            PressureUnit::Hpa => PressureUnit::MmHg,
+        }
+    }
+}
+
+/// Units the readings are displayed in, the other unit is shown next to it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Units {
+    pub temperature: TemperatureUnit,
+    /// `None` keeps mm Hg in the overview and hPa in the pressure chart
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pressure: Option<PressureUnit>,
+}
+
+impl Units {
+    /// Pressure unit of the overview, `watch` and `status`
+    pub fn pressure(&self) -> PressureUnit {
+        self.pressure.unwrap_or_default()
+    }
+
+    /// Pressure unit of the chart, which stays in hPa unless a unit is configured
+    pub fn chart_pressure(&self) -> PressureUnit {
+        self.pressure.unwrap_or(PressureUnit::Hpa)
+    }
+}
+
+/// When the desktop notifications reacting to the measurements show up
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReactionSettings {
+    /// Only on in debug builds unless turned on explicitly
+    pub enabled: bool,
+    /// Light in lux from which a rising brightness asks to close the blinds
+    pub blinds_light: f32,
+    /// Light in lux which asks to close the blinds whatever the trend
+    pub blinds_force_light: f32,
+    /// The blinds are only worth closing during the day, after this hour
+    pub day_after_hour: u32,
+    /// and before this one
+    pub day_before_hour: u32,
+}
+
+impl Default for ReactionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: cfg!(debug_assertions),
+            blinds_light: 800.0,
+            blinds_force_light: 1200.0,
+            day_after_hour: 8,
+            day_before_hour: 20,
+        }
+    }
+}
+
+/// Parts of the settings read all over the app, fixed for the whole run
+#[derive(Default)]
+struct Runtime {
+    ble: BleConfig,
+    thresholds: Thresholds,
+    units: Units,
+    reactions: ReactionSettings,
+}
+
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+/// Validates the sections read all over the app
+pub fn validate(settings: &Settings) -> Result<()> {
+    settings.thresholds.validate()
+}
+
+/// Makes the values of the config file the ones used by the app, has to run before anything
+/// reads them or the defaults stick
+pub fn init(settings: &Settings) {
+    let runtime = Runtime {
+        ble: settings.ble.clone(),
+        thresholds: settings.thresholds.clone(),
+        units: settings.units.clone(),
+        reactions: settings.reactions.clone(),
+    };
+    if RUNTIME.set(runtime).is_err() {
+        tracing::warn!("Runtime config was already initialized");
+    }
+}
+
+fn runtime() -> &'static Runtime {
+    RUNTIME.get_or_init(Runtime::default)
+}
+
+pub fn ble() -> &'static BleConfig {
+    &runtime().ble
+}
+
+pub fn thresholds() -> &'static Thresholds {
+    &runtime().thresholds
+}
+
+pub fn units() -> &'static Units {
+    &runtime().units
+}
+
+pub fn reactions() -> &'static ReactionSettings {
+    &runtime().reactions
+}
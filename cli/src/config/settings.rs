@@ -0,0 +1,337 @@
+use super::runtime::{self, BleConfig, ReactionSettings, Thresholds, Units};
+use crate::{
+    correction::CalibrationProfile,
+    error::{Error, Result},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, io, path::PathBuf, sync::OnceLock};
+use toml_edit::{DocumentMut, Item, Table};
+
+/// Set by `--config`, the settings of the whole run live there instead
+static PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// A peripheral the user picked and gave a human readable name to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceEntry {
+    pub alias: String,
+    pub address: String,
+}
+
+/// Offsets last written to the calibration characteristics of a device, the firmware does
+/// not report them back
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeviceCalibration {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature_adjust: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub humidity_adjust: Option<i32>,
+    /// Corrections applied by the CLI on top of what the firmware adjusts
+    #[serde(default, skip_serializing_if = "CalibrationProfile::is_empty")]
+    pub profile: CalibrationProfile,
+}
+
+/// User configuration persisted between the runs in `$XDG_CONFIG_HOME/co2nsole/config.toml`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Settings {
+    /// Alias or address of the device to connect to when no device is passed explicitly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_device: Option<String>,
+    /// Index or name (e.g. `hci1`) of the Bluetooth adapter, the first one when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adapter: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub devices: Vec<DeviceEntry>,
+    /// Battery levels in percent which trigger a desktop notification when reached
+    #[serde(default = "default_battery_alerts")]
+    pub battery_alerts: Vec<u8>,
+    /// Calibration of each device by its address
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub calibrations: BTreeMap<String, DeviceCalibration>,
+    // the sections below are only written once they differ from the defaults, so a firmware
+    // update of `shared/conf.h` still reaches the users who never changed them
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub ble: BleConfig,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub thresholds: Thresholds,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub units: Units,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub reactions: ReactionSettings,
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// What the item means whatever its formatting and comments, `None` for an item which is not
+/// valid TOML on its own
+fn semantic(item: &Item) -> Option<toml::Value> {
+    let mut document = DocumentMut::new();
+    document.insert("item", item.clone());
+    toml::from_str::<toml::Table>(&document.to_string())
+        .ok()?
+        .remove("item")
+}
+
+/// Copies the values of `changes` which differ into `table`. The keys `changes` does not have
+/// are kept, they are either defaults skipped when serializing or added by hand.
+fn merge(table: &mut Table, changes: &Table) {
+    for (key, change) in changes.iter() {
+        match (table.get_mut(key), change) {
+            (Some(Item::Table(existing)), Item::Table(change)) => merge(existing, change),
+            (Some(existing), _) if semantic(existing) == semantic(change) => {}
+            (Some(Item::Value(existing)), Item::Value(change)) => {
+                // the comments around the value stay with the key
+                let decor = existing.decor().clone();
+                *existing = change.clone();
+                *existing.decor_mut() = decor;
+            }
+            (_, Item::Table(change)) => {
+                let mut added = Table::new();
+                // a table of tables only gets a header when it has values of its own
+                added.set_implicit(true);
+                merge(&mut added, change);
+                table.insert(key, Item::Table(added));
+            }
+            _ => {
+                table.insert(key, change.clone());
+            }
+        }
+    }
+}
+
+fn default_battery_alerts() -> Vec<u8> {
+    vec![20, 10]
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            default_device: None,
+            adapter: None,
+            devices: Vec::new(),
+            battery_alerts: default_battery_alerts(),
+            calibrations: BTreeMap::new(),
+            ble: BleConfig::default(),
+            thresholds: Thresholds::default(),
+            units: Units::default(),
+            reactions: ReactionSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn path() -> Option<PathBuf> {
+        PATH_OVERRIDE
+            .get()
+            .cloned()
+            .or_else(|| dirs::config_dir().map(|dir| dir.join("co2nsole").join("config.toml")))
+    }
+
+    /// Reads and saves the settings from the given file for the rest of the run
+    pub fn use_path(path: PathBuf) {
+        if PATH_OVERRIDE.set(path).is_err() {
+            tracing::warn!("Config path was already set");
+        }
+    }
+
+    /// Reads the settings file, a missing file is the same as the default settings
+    pub fn load() -> Result<Self> {
+        let path = match Self::path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(Self::default()),
+        };
+
+        let content = fs::read_to_string(&path)?;
+        let settings: Self = toml::from_str(&content)
+            .map_err(|e| Error::Config(format!("{}: {e}", path.display())))?;
+        for (device, calibration) in &settings.calibrations {
+            calibration.profile.validate(device)?;
+        }
+        runtime::validate(&settings)?;
+
+        Ok(settings)
+    }
+
+    /// Writes the settings into the config file, only the values which changed are touched so
+    /// the comments, order and formatting of a hand-edited file stay
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()
+            .ok_or_else(|| Error::Config("can not find the config directory".to_string()))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let content = self
+            .merged_into(&content)
+            .map_err(|e| Error::Config(format!("{}: {e}", path.display())))?;
+        fs::write(&path, content)?;
+        tracing::info!("Saved config to {}", path.display());
+
+        Ok(())
+    }
+
+    /// The config file `content` updated with the settings
+    fn merged_into(&self, content: &str) -> Result<String, String> {
+        let mut document = content.parse::<DocumentMut>().map_err(|e| e.to_string())?;
+        let changes = toml::to_string(self)
+            .map_err(|e| e.to_string())?
+            .parse::<DocumentMut>()
+            .map_err(|e| e.to_string())?;
+
+        merge(document.as_table_mut(), changes.as_table());
+        Ok(document.to_string())
+    }
+
+    /// The settings with every default spelled out, a starting point to edit
+    pub fn to_full_toml(&self) -> Result<String> {
+        let to_value = |value: Result<toml::Value, toml::ser::Error>| {
+            value.map_err(|e| Error::Config(e.to_string()))
+        };
+
+        let mut settings = to_value(toml::Value::try_from(self))?;
+        if let Some(table) = settings.as_table_mut() {
+            table.insert(
+                "ble".to_string(),
+                to_value(toml::Value::try_from(&self.ble))?,
+            );
+            table.insert(
+                "thresholds".to_string(),
+                to_value(toml::Value::try_from(&self.thresholds))?,
+            );
+            table.insert(
+                "units".to_string(),
+                to_value(toml::Value::try_from(&self.units))?,
+            );
+            table.insert(
+                "reactions".to_string(),
+                to_value(toml::Value::try_from(&self.reactions))?,
+            );
+        }
+
+        toml::to_string_pretty(&settings).map_err(|e| Error::Config(e.to_string()))
+    }
+
+    /// Resolves an alias to the address, anything that is not a known alias is treated
+    /// as the address itself
+    pub fn resolve_device(&self, alias_or_address: &str) -> String {
+        self.devices
+            .iter()
+            .find(|device| device.alias == alias_or_address)
+            .map(|device| device.address.clone())
+            .unwrap_or(alias_or_address.to_string())
+    }
+
+    /// Corrections of the device readings, an empty profile when none is configured
+    pub fn profile_of(&self, device: &str) -> CalibrationProfile {
+        self.calibrations
+            .get(device)
+            .map(|calibration| calibration.profile)
+            .unwrap_or_default()
+    }
+
+    pub fn alias_of(&self, address: &str) -> Option<&str> {
+        self.devices
+            .iter()
+            .find(|device| device.address.eq_ignore_ascii_case(address))
+            .map(|device| device.alias.as_str())
+    }
+
+    /// Stores the alias for the address and makes it the default device
+    pub fn remember_device(&mut self, alias: String, address: String) {
+        self.devices.retain(|device| {
+            device.alias != alias && !device.address.eq_ignore_ascii_case(&address)
+        });
+        self.default_device = Some(alias.clone());
+        self.devices.push(DeviceEntry { alias, address });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HAND_EDITED: &str = r#"# picked with `co2nsole scan`
+default_device = "desk" # mine
+
+[[devices]]
+alias = "desk"
+address = "AA:BB:CC:DD:EE:FF"
+
+[units]
+# the thermometer on the wall is in °F
+temperature = "fahrenheit"
+pressure = "hpa"
+
+[calibrations."AA:BB:CC:DD:EE:FF"]
+temperature_adjust = -7 # against the wall thermometer
+
+[calibrations."AA:BB:CC:DD:EE:FF".profile]
+co2 = { type = "offset", offset = -20.0 }
+"#;
+
+    fn hand_edited() -> Settings {
+        toml::from_str(HAND_EDITED).unwrap()
+    }
+
+    #[test]
+    fn unchanged_settings_keep_the_file() {
+        let content = hand_edited().merged_into(HAND_EDITED).unwrap();
+        // the only key written is the one the file did not have
+        assert_eq!(
+            content.replace("battery_alerts = [20, 10]\n", ""),
+            HAND_EDITED
+        );
+    }
+
+    #[test]
+    fn changed_values_keep_their_comments() {
+        let mut settings = hand_edited();
+        settings
+            .calibrations
+            .get_mut("AA:BB:CC:DD:EE:FF")
+            .unwrap()
+            .temperature_adjust = Some(-6);
+        settings.remember_device("window".to_string(), "11:22:33:44:55:66".to_string());
+
+        let content = settings.merged_into(HAND_EDITED).unwrap();
+        assert!(content.contains("default_device = \"window\" # mine\n"));
+        assert!(content.contains("temperature_adjust = -6 # against the wall thermometer\n"));
+        assert!(content.contains("# the thermometer on the wall is in °F\n"));
+        assert!(content.contains("alias = \"window\""));
+
+        let saved: Settings = toml::from_str(&content).unwrap();
+        assert_eq!(saved.devices.len(), 2);
+        assert_eq!(
+            saved.profile_of("AA:BB:CC:DD:EE:FF"),
+            settings.profile_of("AA:BB:CC:DD:EE:FF")
+        );
+    }
+
+    #[test]
+    fn new_device_calibration_is_added() {
+        let mut settings = hand_edited();
+        settings
+            .calibrations
+            .entry("11:22:33:44:55:66".to_string())
+            .or_default()
+            .humidity_adjust = Some(3);
+
+        let content = settings.merged_into(HAND_EDITED).unwrap();
+        let saved: Settings = toml::from_str(&content).unwrap();
+        assert_eq!(
+            saved.calibrations["11:22:33:44:55:66"].humidity_adjust,
+            Some(3)
+        );
+        assert_eq!(
+            saved.calibrations["AA:BB:CC:DD:EE:FF"].temperature_adjust,
+            Some(-7)
+        );
+        assert!(content.starts_with("# picked with `co2nsole scan`\n"));
+    }
+}
@@ -0,0 +1,86 @@
+use rand::Rng;
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+const BACKOFF_MAX: Duration = Duration::from_secs(60);
+/// Doubling the base delay more times than this already reaches the max
+const BACKOFF_MAX_EXPONENT: u32 = 6;
+
+/// Where a device is in its connection lifecycle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConnectionState {
+    /// Looking for the peripheral in range
+    Scanning,
+    Connecting,
+    /// Connected, looking up the services and waiting for the first measurement
+    Discovering,
+    Streaming,
+    /// Still connected but the measurements stopped coming
+    Degraded,
+    /// Waiting before the next connection attempt
+    BackingOff {
+        attempt: u32,
+        retry_at: Instant,
+    },
+    /// The source has no more measurements, e.g. a recording was played to the end
+    Finished,
+}
+
+/// Receives the state changes while a source is connecting
+pub type StateListener = dyn Fn(ConnectionState) + Send + Sync;
+
+impl Display for ConnectionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectionState::Scanning => write!(f, "scanning"),
+            ConnectionState::Connecting => write!(f, "connecting"),
+            ConnectionState::Discovering => write!(f, "discovering"),
+            ConnectionState::Streaming => write!(f, "streaming"),
+            ConnectionState::Degraded => write!(f, "degraded"),
+            ConnectionState::BackingOff { attempt, retry_at } => write!(
+                f,
+                "backing off for {}s (attempt {attempt})",
+                retry_at.saturating_duration_since(Instant::now()).as_secs()
+            ),
+            ConnectionState::Finished => write!(f, "finished"),
+        }
+    }
+}
+
+/// Exponential delay between the reconnection attempts. The jitter keeps several devices
+/// lost at the same time (e.g. the adapter was reset) from reconnecting in lockstep.
+#[derive(Debug, Default)]
+pub struct Backoff {
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = BACKOFF_BASE
+            .saturating_mul(1 << self.attempt.min(BACKOFF_MAX_EXPONENT))
+            .min(BACKOFF_MAX);
+        self.attempt += 1;
+
+        delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+
+    /// Skips straight to the longest delay
+    pub fn max_out(&mut self) {
+        self.attempt = self.attempt.max(BACKOFF_MAX_EXPONENT);
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
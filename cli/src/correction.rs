@@ -0,0 +1,276 @@
+use crate::{
+    climate_data::ClimateData,
+    error::{Error, Result},
+};
+use serde::{Deserialize, Serialize};
+
+/// Maps a raw reading to the corrected one, configured per metric in the config file e.g.
+/// `temperature = { type = "offset", offset = -1.5 }`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Correction {
+    /// Adds the offset to the reading, e.g. the self-heating of the board
+    Offset { offset: f32 },
+    /// `reading * scale + offset`
+    Linear {
+        scale: f32,
+        #[serde(default)]
+        offset: f32,
+    },
+    /// Line through two raw readings and the reference values taken at the same time
+    TwoPoint { raw: [f32; 2], reference: [f32; 2] },
+}
+
+impl Correction {
+    pub fn apply(&self, value: f32) -> f32 {
+        match *self {
+            Correction::Offset { offset } => value + offset,
+            Correction::Linear { scale, offset } => value * scale + offset,
+            Correction::TwoPoint { raw, reference } => {
+                let scale = (reference[1] - reference[0]) / (raw[1] - raw[0]);
+                reference[0] + (value - raw[0]) * scale
+            }
+        }
+    }
+
+    fn validate(&self) -> std::result::Result<(), &'static str> {
+        let values = match self {
+            Correction::Offset { offset } => vec![*offset],
+            Correction::Linear { scale, offset } => vec![*scale, *offset],
+            Correction::TwoPoint { raw, reference } => {
+                if raw[0] == raw[1] {
+                    return Err("two-point correction needs two different raw readings");
+                }
+                [*raw, *reference].concat()
+            }
+        };
+
+        match values.iter().all(|value| value.is_finite()) {
+            true => Ok(()),
+            false => Err("correction values must be finite numbers"),
+        }
+    }
+}
+
+/// Corrections the CLI applies to the readings of a device before they get displayed, for
+/// what is easier to keep on the host than to flash or for a firmware that can not be
+/// reflashed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub co2: Option<Correction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<Correction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub humidity: Option<Correction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pressure: Option<Correction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub light: Option<Correction>,
+}
+
+impl CalibrationProfile {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn corrections(&self) -> [(&'static str, Option<Correction>); 5] {
+        [
+            ("co2", self.co2),
+            ("temperature", self.temperature),
+            ("humidity", self.humidity),
+            ("pressure", self.pressure),
+            ("light", self.light),
+        ]
+    }
+
+    /// Rejects the corrections which would turn every reading into garbage
+    pub fn validate(&self, device: &str) -> Result<()> {
+        for (metric, correction) in self.corrections() {
+            if let Some(Err(reason)) = correction.map(|correction| correction.validate()) {
+                return Err(Error::Config(format!(
+                    "calibration profile of {device}, {metric}: {reason}"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn apply(&self, data: &ClimateData) -> ClimateData {
+        let correct = |correction: Option<Correction>, value: f32| {
+            correction.map_or(value, |correction| correction.apply(value))
+        };
+
+        ClimateData {
+            co2: data
+                .co2
+                .map(|co2| correct(self.co2, co2 as f32).round() as i32),
+            temperature: correct(self.temperature, data.temperature),
+            humidity: correct(self.humidity, data.humidity),
+            pressure: correct(self.pressure, data.pressure),
+            light: data.light.map(|light| correct(self.light, light)),
+            ..*data
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::climate_data::SensorFaults;
+
+    fn measurement() -> ClimateData {
+        serde_json::from_str(
+            r#"{"co2": 600, "temperature": 22.0, "eco2": 450, "etvoc": 12, "pressure": 1000.0,
+                "humidity": 40.0, "light": 200.0, "error_flags": 4}"#,
+        )
+        .unwrap()
+    }
+
+    fn readings(data: &ClimateData) -> (Option<i32>, f32, f32, f32, Option<f32>) {
+        (
+            data.co2,
+            data.temperature,
+            data.humidity,
+            data.pressure,
+            data.light,
+        )
+    }
+
+    #[test]
+    fn empty_profile_keeps_readings() {
+        let profile = CalibrationProfile::default();
+        let data = measurement();
+        let corrected = profile.apply(&data);
+
+        assert!(profile.is_empty());
+        assert_eq!(readings(&corrected), readings(&data));
+        assert_eq!(corrected.eco2, data.eco2);
+        assert_eq!(corrected.etvoc, data.etvoc);
+        assert_eq!(corrected.error_flags, SensorFaults::BH1750);
+        assert_eq!(corrected.timestamp, data.timestamp);
+    }
+
+    #[test]
+    fn offset_per_quantity() {
+        let offset = Some(Correction::Offset { offset: -2.5 });
+        let data = measurement();
+        let cases = [
+            (
+                CalibrationProfile {
+                    co2: offset,
+                    ..Default::default()
+                },
+                (Some(598), 22.0, 40.0, 1000.0, Some(200.0)),
+            ),
+            (
+                CalibrationProfile {
+                    temperature: offset,
+                    ..Default::default()
+                },
+                (Some(600), 19.5, 40.0, 1000.0, Some(200.0)),
+            ),
+            (
+                CalibrationProfile {
+                    humidity: offset,
+                    ..Default::default()
+                },
+                (Some(600), 22.0, 37.5, 1000.0, Some(200.0)),
+            ),
+            (
+                CalibrationProfile {
+                    pressure: offset,
+                    ..Default::default()
+                },
+                (Some(600), 22.0, 40.0, 997.5, Some(200.0)),
+            ),
+            (
+                CalibrationProfile {
+                    light: offset,
+                    ..Default::default()
+                },
+                (Some(600), 22.0, 40.0, 1000.0, Some(197.5)),
+            ),
+        ];
+
+        for (profile, expected) in cases {
+            assert_eq!(readings(&profile.apply(&data)), expected, "{profile:?}");
+        }
+    }
+
+    #[test]
+    fn scale_per_quantity() {
+        let scale = Some(Correction::Linear {
+            scale: 1.5,
+            offset: 1.0,
+        });
+        let profile = CalibrationProfile {
+            co2: scale,
+            temperature: scale,
+            humidity: scale,
+            pressure: scale,
+            light: scale,
+        };
+
+        assert_eq!(
+            readings(&profile.apply(&measurement())),
+            (Some(901), 34.0, 61.0, 1501.0, Some(301.0))
+        );
+    }
+
+    #[test]
+    fn two_point_maps_raw_to_reference() {
+        let correction = Correction::TwoPoint {
+            raw: [410.0, 1020.0],
+            reference: [420.0, 1000.0],
+        };
+
+        assert_eq!(correction.apply(410.0), 420.0);
+        assert_eq!(correction.apply(1020.0), 1000.0);
+        assert!((correction.apply(715.0) - 710.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn missing_readings_stay_missing() {
+        let mut data = measurement();
+        data.co2 = None;
+        data.light = None;
+        let profile = CalibrationProfile {
+            co2: Some(Correction::Offset { offset: 10.0 }),
+            light: Some(Correction::Offset { offset: 10.0 }),
+            ..Default::default()
+        };
+
+        let corrected = profile.apply(&data);
+        assert_eq!(corrected.co2, None);
+        assert_eq!(corrected.light, None);
+    }
+
+    #[test]
+    fn invalid_corrections_are_rejected() {
+        let profile = CalibrationProfile {
+            humidity: Some(Correction::TwoPoint {
+                raw: [40.0, 40.0],
+                reference: [45.0, 60.0],
+            }),
+            ..Default::default()
+        };
+        assert!(matches!(
+            profile.validate("simulator"),
+            Err(Error::Config(_))
+        ));
+
+        let profile = CalibrationProfile {
+            pressure: Some(Correction::Linear {
+                scale: f32::NAN,
+                offset: 0.0,
+            }),
+            ..Default::default()
+        };
+        assert!(matches!(
+            profile.validate("simulator"),
+            Err(Error::Config(_))
+        ));
+    }
+}
@@ -0,0 +1,409 @@
+use crate::{
+    battery,
+    ble_actions::BleAction,
+    bluetooth::{self, LinkStats},
+    calibration::CalibrationState,
+    climate_data::ClimateData,
+    config::{self, settings::Settings},
+    connection::{Backoff, ConnectionState},
+    correction::CalibrationProfile,
+    error::{Error, Result},
+    history::{BatteryHistory, History},
+    sources::{self, SourceKind},
+    window_sensor::{self, WindowSensor},
+};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::mpsc,
+    time::{interval, sleep},
+};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+/// The firmware notifies every couple of seconds, a longer gap means a weak link
+const DEGRADED_AFTER: Duration = Duration::from_secs(10);
+/// How often we look for co2nsoles that were turned on after the CLI started
+const DISCOVERY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Called for every measurement received from any of the devices
+pub type OnData = Arc<dyn Fn(&Device, ClimateData) + Send + Sync>;
+
+/// A single sensor with its own history and connection
+pub struct Device {
+    /// BLE address or the source description, unique across the devices
+    pub id: String,
+    /// Short name displayed in the device tab bar
+    pub label: String,
+    pub history: RwLock<History>,
+    pub battery: RwLock<BatteryHistory>,
+    pub link: LinkStats,
+    /// As reported on the last connect, `None` until then or when it can not be calibrated
+    pub calibration: RwLock<Option<CalibrationState>>,
+    /// Applied to every measurement before it gets into the history
+    pub profile: CalibrationProfile,
+    actions: Mutex<Option<mpsc::Sender<BleAction>>>,
+    state: RwLock<ConnectionState>,
+    last_data: Mutex<Option<Instant>>,
+    last_error: Mutex<Option<String>>,
+}
+
+impl Device {
+    fn new(id: String, label: String, profile: CalibrationProfile) -> Self {
+        Self {
+            id,
+            label,
+            history: RwLock::new(History::new()),
+            battery: RwLock::new(BatteryHistory::new()),
+            link: LinkStats::default(),
+            calibration: RwLock::new(None),
+            profile,
+            actions: Mutex::new(None),
+            state: RwLock::new(ConnectionState::Connecting),
+            last_data: Mutex::new(None),
+            last_error: Mutex::new(None),
+        }
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        *self.state.read().unwrap()
+    }
+
+    fn set_state(&self, state: ConnectionState) {
+        let mut current = self.state.write().unwrap();
+        if *current != state {
+            tracing::debug!("{} is {}", self.label, state);
+            *current = state;
+        }
+    }
+
+    /// Why the last connection attempt or session failed
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().unwrap().clone()
+    }
+
+    fn fail(&self, error: &Error, backoff: &mut Backoff) {
+        if error.needs_user() {
+            backoff.max_out();
+        }
+
+        *self.last_error.lock().unwrap() = Some(error.to_string());
+    }
+
+    /// Time since the last measurement of the current connection
+    pub fn silence(&self) -> Option<Duration> {
+        self.last_data.lock().unwrap().map(|at| at.elapsed())
+    }
+
+    /// Sends the action to the currently open connection of the device, if any
+    pub async fn send(&self, action: BleAction) {
+        let sender = self.actions.lock().unwrap().clone();
+
+        match sender {
+            Some(sender) => {
+                if let Err(err) = sender.send(action).await {
+                    tracing::error!("Failed to send action to {}: {:?}", self.label, err);
+                }
+            }
+            None => tracing::warn!("Device {} is not connected, action ignored", self.label),
+        }
+    }
+}
+
+/// All the devices the CLI is connected to and the one displayed in the TUI
+#[derive(Default)]
+pub struct Devices {
+    list: RwLock<Vec<Arc<Device>>>,
+    selected: AtomicUsize,
+    /// Shared by all the main sensors, connected only when the CLI uses Bluetooth
+    pub window: Arc<WindowSensor>,
+}
+
+impl Devices {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn all(&self) -> Vec<Arc<Device>> {
+        self.list.read().unwrap().clone()
+    }
+
+    pub fn find(&self, id: &str) -> Option<Arc<Device>> {
+        self.list
+            .read()
+            .unwrap()
+            .iter()
+            .find(|device| device.id == id)
+            .cloned()
+    }
+
+    fn register(&self, id: String, label: String, settings: &Settings) -> Arc<Device> {
+        let profile = settings.profile_of(&id);
+        if !profile.is_empty() {
+            tracing::info!("Correcting the readings of {label} with its calibration profile");
+        }
+
+        let device = Arc::new(Device::new(id, label, profile));
+        self.list.write().unwrap().push(Arc::clone(&device));
+
+        device
+    }
+
+    pub fn selected_index(&self) -> usize {
+        self.selected.load(Ordering::Relaxed)
+    }
+
+    pub fn selected(&self) -> Option<Arc<Device>> {
+        self.list
+            .read()
+            .unwrap()
+            .get(self.selected_index())
+            .cloned()
+    }
+
+    pub fn is_selected(&self, device: &Device) -> bool {
+        self.selected()
+            .is_some_and(|selected| selected.id == device.id)
+    }
+
+    pub fn select(&self, index: usize) {
+        if index < self.list.read().unwrap().len() {
+            self.selected.store(index, Ordering::Relaxed);
+        }
+    }
+
+    pub fn select_next(&self) {
+        let len = self.list.read().unwrap().len();
+        if len > 0 {
+            self.select((self.selected_index() + 1) % len);
+        }
+    }
+
+    pub fn select_previous(&self) {
+        let len = self.list.read().unwrap().len();
+        if len > 0 {
+            self.select((self.selected_index() + len - 1) % len);
+        }
+    }
+}
+
+/// Marks the device degraded while the measurements do not come, the source itself decides
+/// when the silence is long enough to give up on the connection
+async fn watch_stream(device: &Device) -> Result<()> {
+    let mut ticker = interval(Duration::from_secs(1));
+
+    loop {
+        ticker.tick().await;
+
+        let silent = device
+            .silence()
+            .is_some_and(|silence| silence > DEGRADED_AFTER);
+        match device.state() {
+            ConnectionState::Streaming if silent => device.set_state(ConnectionState::Degraded),
+            ConnectionState::Degraded if !silent => device.set_state(ConnectionState::Streaming),
+            _ => {}
+        }
+    }
+}
+
+/// Keeps the device connected for the lifetime of the app. A failure only reconnects this
+/// device and never affects the others.
+async fn run_device(
+    device: Arc<Device>,
+    kind: SourceKind,
+    settings: Arc<Settings>,
+    on_data: OnData,
+) {
+    let mut backoff = Backoff::new();
+    let mut connected_before = false;
+
+    loop {
+        tracing::debug!("Connecting to {}", device.label);
+        device.set_state(ConnectionState::Connecting);
+
+        let on_state = {
+            let device = Arc::clone(&device);
+            move |state| device.set_state(state)
+        };
+        let source = match sources::connect(&kind, &on_state).await {
+            Ok(source) => Some(source),
+            Err(e) => {
+                tracing::error!("Failed to connect to {}: {}", device.label, e);
+                device.fail(&e, &mut backoff);
+                None
+            }
+        };
+
+        if let Some(source) = source {
+            tracing::debug!("Connected to {}", source.describe());
+            if connected_before {
+                device.link.record_reconnect();
+            }
+            connected_before = true;
+
+            match source.read_calibration().await {
+                Ok(calibration) => {
+                    *device.calibration.write().unwrap() = calibration.map(|calibration| {
+                        calibration.with_adjusts(settings.calibrations.get(&device.id))
+                    })
+                }
+                // not worth dropping the connection, the measurements matter more
+                Err(e) => tracing::warn!("Failed to read calibration of {}: {e}", device.label),
+            }
+
+            let (tx, rx) = mpsc::channel(100);
+            *device.actions.lock().unwrap() = Some(tx);
+            *device.last_data.lock().unwrap() = None;
+            device.set_state(ConnectionState::Discovering);
+
+            let mut on_device_data = |data: ClimateData| {
+                *device.last_data.lock().unwrap() = Some(Instant::now());
+                *device.last_error.lock().unwrap() = None;
+                if device.state() != ConnectionState::Streaming {
+                    device.set_state(ConnectionState::Streaming);
+                }
+
+                on_data(&device, data)
+            };
+            let mut on_battery = |level: u8| {
+                let previous = device.battery.write().unwrap().capture_level(level);
+                battery::check_level(&device.label, previous, level, &settings.battery_alerts);
+            };
+            // whatever finishes first (a stop request or a broken stream) ends the session
+            let mut exhausted = false;
+            if let Err(e) = tokio::select! {
+                result = source.run_actions(rx) => result,
+                result = source.subscribe(&mut on_device_data, &device.link) => {
+                    exhausted = result.is_ok();
+                    result
+                }
+                result = source.watch_battery(&mut on_battery) => result,
+                result = watch_stream(&device) => result,
+            } {
+                tracing::error!("Error in {} connection: {:?}", source.describe(), e);
+                device.fail(&e, &mut backoff);
+            }
+
+            // a session which delivered data was a success, the next failure starts over
+            if device.last_data.lock().unwrap().is_some() {
+                backoff.reset();
+            }
+
+            *device.actions.lock().unwrap() = None;
+            source.disconnect().await;
+
+            // nothing to reconnect to, the measurements stay on the screen
+            if exhausted {
+                tracing::info!("{} has no more measurements", source.describe());
+                device.set_state(ConnectionState::Finished);
+                return;
+            }
+        }
+
+        let delay = backoff.next_delay();
+        device.set_state(ConnectionState::BackingOff {
+            attempt: backoff.attempt(),
+            retry_at: Instant::now() + delay,
+        });
+        sleep(delay).await;
+    }
+}
+
+/// Registers the devices of the given source kind and keeps all of them connected
+pub async fn supervise(
+    kind: SourceKind,
+    settings: Arc<Settings>,
+    devices: Arc<Devices>,
+    on_data: OnData,
+) {
+    if kind.uses_bluetooth() {
+        tokio::spawn(window_sensor::run(
+            Arc::clone(&devices.window),
+            Arc::clone(&settings),
+        ));
+    }
+
+    if let SourceKind::Replay { path, speed, .. } = &kind {
+        let recorded = match sources::recorded_devices(path).await {
+            Ok(recorded) if !recorded.is_empty() => recorded,
+            // a single device reports what is wrong with the recording
+            Ok(_) => vec![None],
+            Err(e) => {
+                tracing::error!("Failed to read the devices of {}: {e}", path.display());
+                vec![None]
+            }
+        };
+
+        let replays = recorded.into_iter().map(|recorded| {
+            let id = recorded.clone().unwrap_or(kind.to_string());
+            let label = settings.alias_of(&id).unwrap_or(&id).to_string();
+            let device = devices.register(id, label, &settings);
+            let kind = SourceKind::Replay {
+                path: path.clone(),
+                speed: *speed,
+                device: recorded,
+            };
+
+            run_device(device, kind, Arc::clone(&settings), Arc::clone(&on_data))
+        });
+        futures::future::join_all(replays).await;
+        return;
+    }
+
+    if !matches!(kind, SourceKind::Ble) {
+        let label = match &kind {
+            SourceKind::BleDevice { address } => settings.alias_of(address).map(str::to_string),
+            _ => None,
+        };
+
+        let device = devices.register(
+            kind.to_string(),
+            label.unwrap_or(kind.to_string()),
+            &settings,
+        );
+        return run_device(device, kind, settings, on_data).await;
+    }
+
+    let ble = config::ble();
+
+    loop {
+        tracing::debug!("Looking for sensors...");
+        match bluetooth::discover(&ble.main_local_name, ble.main_service).await {
+            Ok(discovered) => {
+                for peripheral in discovered {
+                    if devices.find(&peripheral.address).is_some() {
+                        continue;
+                    }
+
+                    tracing::info!("Found {} at {}", peripheral.local_name, peripheral.address);
+                    let label = match settings.alias_of(&peripheral.address) {
+                        Some(alias) => alias.to_string(),
+                        None => format!("{} {}", peripheral.local_name, peripheral.address),
+                    };
+                    let device = devices.register(peripheral.address.clone(), label, &settings);
+
+                    tokio::spawn(run_device(
+                        device,
+                        SourceKind::BleDevice {
+                            address: peripheral.address,
+                        },
+                        Arc::clone(&settings),
+                        Arc::clone(&on_data),
+                    ));
+                }
+            }
+            Err(e) => tracing::error!("Failed to discover sensors: {}", e),
+        }
+
+        // keep scanning often until the very first sensor shows up
+        if devices.all().is_empty() {
+            sleep(RECONNECT_DELAY).await;
+        } else {
+            sleep(DISCOVERY_INTERVAL).await;
+        }
+    }
+}
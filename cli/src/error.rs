@@ -0,0 +1,72 @@
+use crate::bluetooth::AdapterError;
+use btleplug::api::CharPropFlags;
+use std::{path::PathBuf, time::Duration};
+use uuid::Uuid;
+
+/// Everything that can go wrong while talking to the sensors. The variants are split by what
+/// the caller can do about them: a missing adapter needs the user, a lost link only needs a
+/// reconnect and a bad payload can be skipped.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Adapter(#[from] AdapterError),
+    #[error("Bluetooth error: {0}")]
+    Bluetooth(#[from] btleplug::Error),
+    #[error("No {0} found, make sure it is powered on and in range")]
+    NotFound(String),
+    #[error(
+        "Found {} devices: {}. Pick one with --device or remember it with `co2nsole scan`",
+        .0.len(),
+        .0.join(", ")
+    )]
+    SeveralDevices(Vec<String>),
+    #[error("Failed to connect to {0}")]
+    ConnectionFailed(String),
+    #[error("BLE connection was lost")]
+    ConnectionLost,
+    #[error("Characteristic {uuid} supporting {property:?} not found")]
+    CharacteristicNotFound { uuid: Uuid, property: CharPropFlags },
+    #[error("Timed out while {0}")]
+    Timeout(&'static str),
+    #[error("No data received for {}s", .0.as_secs())]
+    NoData(Duration),
+    #[error("Failed to decode {:?}: {reason}", String::from_utf8_lossy(payload))]
+    Decode { payload: Vec<u8>, reason: String },
+    #[error("{0} was closed")]
+    SourceClosed(String),
+    #[error("The device rejected the {0} calibration")]
+    CalibrationRejected(&'static str),
+    #[error("{0} can not be calibrated: {1}")]
+    CalibrationUnavailable(String, &'static str),
+    #[error("{0}")]
+    InvalidReference(String),
+    #[error("Recording {} does not exist", .0.display())]
+    RecordingNotFound(PathBuf),
+    #[error("Invalid config: {0}")]
+    Config(String),
+    #[error("Invalid UUID: {0}")]
+    Uuid(#[from] uuid::Error),
+    #[error("Serial port error: {0}")]
+    Serial(#[from] tokio_serial::Error),
+    #[error("Failed to show the notification: {0}")]
+    Notification(#[from] notify_rust::error::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Task(#[from] tokio::task::JoinError),
+}
+
+impl Error {
+    /// Failures which will not go away by reconnecting until the user fixes something
+    pub fn needs_user(&self) -> bool {
+        matches!(
+            self,
+            Error::Adapter(_)
+                | Error::Config(_)
+                | Error::RecordingNotFound(_)
+                | Error::InvalidReference(_)
+        )
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,307 @@
+use crate::climate_data::{BINARY_V1_SIZE, FORMAT_BINARY_V1};
+use std::time::{Duration, Instant};
+
+/// Upper bound for a single JSON message, anything bigger is considered garbage on the line
+const MAX_FRAME_SIZE: usize = 4096;
+
+/// Splits a continuous byte stream into separate top-level JSON objects.
+///
+/// The firmware prints a (pretty formatted) JSON object per measurement mixed with plain text
+/// debug logs, so everything outside of the balanced `{ ... }` pair is skipped.
+#[derive(Debug, Default)]
+pub struct JsonFramer {
+    buffer: Vec<u8>,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl JsonFramer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.depth = 0;
+        self.in_string = false;
+        self.escaped = false;
+    }
+
+    /// Whether an object was started but not closed yet
+    pub fn is_pending(&self) -> bool {
+        self.depth > 0
+    }
+
+    /// Feeds a chunk of bytes and returns every JSON object completed by this chunk
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Vec<u8>> {
+        let mut frames = Vec::new();
+
+        for &byte in bytes {
+            if self.depth == 0 {
+                if byte == b'{' {
+                    self.buffer.push(byte);
+                    self.depth = 1;
+                }
+
+                continue;
+            }
+
+            self.buffer.push(byte);
+
+            if self.in_string {
+                match byte {
+                    _ if self.escaped => self.escaped = false,
+                    b'\\' => self.escaped = true,
+                    b'"' => self.in_string = false,
+                    _ => (),
+                }
+            } else {
+                match byte {
+                    b'"' => self.in_string = true,
+                    b'{' => self.depth += 1,
+                    b'}' => {
+                        self.depth -= 1;
+                        if self.depth == 0 {
+                            frames.push(std::mem::take(&mut self.buffer));
+                        }
+                    }
+                    _ => (),
+                }
+            }
+
+            if self.buffer.len() > MAX_FRAME_SIZE {
+                tracing::warn!("Dropping {} bytes of unterminated JSON", self.buffer.len());
+                self.reset();
+            }
+        }
+
+        frames
+    }
+}
+
+/// The fragments of a message are notified right after each other, so anything older is a
+/// leftover of a message whose rest got lost
+const FRAGMENT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Puts measurements split over several BLE notifications back together.
+///
+/// The firmware splits messages bigger than the negotiated MTU into consecutive notifications.
+/// A JSON message is complete with its closing brace, a binary one has the fixed size of its
+/// format. Notifications in an unknown format are passed on as they are.
+#[derive(Debug, Default)]
+pub struct NotificationFramer {
+    json: JsonFramer,
+    binary: Vec<u8>,
+    /// Notifications buffered for the incomplete message
+    fragments: usize,
+    started_at: Option<Instant>,
+}
+
+impl NotificationFramer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Throws away the incomplete message and returns how many notifications it had
+    fn drop_pending(&mut self) -> usize {
+        self.json.reset();
+        self.binary.clear();
+        self.started_at = None;
+        std::mem::take(&mut self.fragments)
+    }
+
+    fn complete(&mut self) {
+        self.fragments = 0;
+        self.started_at = None;
+    }
+
+    /// Feeds a notification and returns the messages it completed together with the number
+    /// of fragments dropped because their message timed out
+    pub fn push(&mut self, notification: Vec<u8>) -> (Vec<Vec<u8>>, usize) {
+        let mut dropped = 0;
+        if self
+            .started_at
+            .is_some_and(|at| at.elapsed() > FRAGMENT_TIMEOUT)
+        {
+            dropped = self.drop_pending();
+            tracing::warn!("Dropped {dropped} fragments of an incomplete message");
+        }
+
+        // JSON text never contains the format byte, so the rest of the pending JSON message
+        // was lost and a binary one starts
+        if self.json.is_pending() && notification.first() == Some(&FORMAT_BINARY_V1) {
+            let lost = self.drop_pending();
+            tracing::warn!("Dropped {lost} fragments of a JSON message cut by a binary one");
+            dropped += lost;
+        }
+
+        let is_binary = if self.fragments > 0 {
+            !self.binary.is_empty()
+        } else {
+            match notification.first() {
+                Some(b'{') => false,
+                Some(&FORMAT_BINARY_V1) => true,
+                _ => return (vec![notification], dropped),
+            }
+        };
+
+        let messages = if is_binary {
+            self.binary.extend_from_slice(&notification);
+            if self.binary.len() >= BINARY_V1_SIZE {
+                // a longer message is passed on as well so decoding reports it
+                vec![std::mem::take(&mut self.binary)]
+            } else {
+                Vec::new()
+            }
+        } else {
+            self.json.push(&notification)
+        };
+
+        if is_binary && self.binary.is_empty() || !is_binary && !self.json.is_pending() {
+            self.complete();
+        } else {
+            self.fragments += 1;
+            self.started_at.get_or_insert_with(Instant::now);
+        }
+
+        (messages, dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEASUREMENT: &[u8] = br#"{
+  "temperature": 23.5,
+  "pressure": 1013.2,
+  "humidity": 41.3,
+  "light": 120,
+  "co2": 612,
+  "eco2": 400,
+  "etvoc": 0
+}"#;
+
+    #[test]
+    fn json_object_split_over_chunks() {
+        let mut framer = JsonFramer::new();
+        let (head, tail) = MEASUREMENT.split_at(40);
+
+        assert!(framer.push(head).is_empty());
+        assert!(framer.is_pending());
+        assert_eq!(framer.push(tail), vec![MEASUREMENT.to_vec()]);
+        assert!(!framer.is_pending());
+    }
+
+    #[test]
+    fn several_json_objects_in_one_chunk() {
+        let mut framer = JsonFramer::new();
+        let chunk = [MEASUREMENT, MEASUREMENT, MEASUREMENT].concat();
+
+        assert_eq!(framer.push(&chunk), vec![MEASUREMENT.to_vec(); 3]);
+    }
+
+    #[test]
+    fn json_skips_text_between_objects() {
+        let mut framer = JsonFramer::new();
+        let chunk = [
+            b"BME280 ready\r\n".as_slice(),
+            MEASUREMENT,
+            b"\r\nCO2: 612 } ppm\r\n",
+            MEASUREMENT,
+        ]
+        .concat();
+
+        assert_eq!(framer.push(&chunk), vec![MEASUREMENT.to_vec(); 2]);
+    }
+
+    fn binary_measurement() -> Vec<u8> {
+        let mut frame = vec![FORMAT_BINARY_V1];
+        frame.extend([0x2E, 0x09, 0x94, 0x27, 0x22, 0x10, 0x78, 0, 0x64, 0x02]);
+        frame.extend([0xC2, 0x01, 0x11, 0, 0, 0]);
+        assert_eq!(frame.len(), BINARY_V1_SIZE);
+
+        frame
+    }
+
+    /// Makes the pending message of the framer older than [`FRAGMENT_TIMEOUT`]
+    fn expire(framer: &mut NotificationFramer) {
+        framer.started_at = Some(Instant::now() - FRAGMENT_TIMEOUT * 2);
+    }
+
+    #[test]
+    fn notifications_reassemble_json_in_order() {
+        let mut framer = NotificationFramer::new();
+        let mut chunks = MEASUREMENT.chunks(20);
+        let last = chunks.next_back().unwrap();
+
+        for chunk in chunks {
+            assert_eq!(framer.push(chunk.to_vec()), (vec![], 0));
+        }
+        assert_eq!(framer.push(last.to_vec()), (vec![MEASUREMENT.to_vec()], 0));
+        assert_eq!(framer.fragments, 0);
+    }
+
+    #[test]
+    fn notifications_reassemble_binary_in_order() {
+        let mut framer = NotificationFramer::new();
+        let frame = binary_measurement();
+        let (head, tail) = frame.split_at(10);
+
+        assert_eq!(framer.push(head.to_vec()), (vec![], 0));
+        assert_eq!(framer.push(tail.to_vec()), (vec![frame.clone()], 0));
+        // a whole frame in one notification goes straight through
+        assert_eq!(framer.push(frame.clone()), (vec![frame], 0));
+    }
+
+    #[test]
+    fn notifications_drop_timed_out_fragments() {
+        let mut framer = NotificationFramer::new();
+        let (head, _) = MEASUREMENT.split_at(20);
+        let (middle, _) = MEASUREMENT[20..].split_at(20);
+
+        framer.push(head.to_vec());
+        framer.push(middle.to_vec());
+        expire(&mut framer);
+
+        // the next message starts from scratch instead of continuing the stale one
+        let frame = binary_measurement();
+        assert_eq!(framer.push(frame.clone()), (vec![frame], 2));
+    }
+
+    #[test]
+    fn notifications_pass_on_out_of_sequence_fragment() {
+        let mut framer = NotificationFramer::new();
+        // the first notification of the message got lost
+        let orphan = MEASUREMENT[20..40].to_vec();
+
+        assert_eq!(framer.push(orphan.clone()), (vec![orphan], 0));
+        assert_eq!(
+            framer.push(MEASUREMENT.to_vec()),
+            (vec![MEASUREMENT.to_vec()], 0)
+        );
+    }
+
+    #[test]
+    fn notifications_binary_cuts_pending_json() {
+        let mut framer = NotificationFramer::new();
+        let (head, _) = MEASUREMENT.split_at(30);
+        let frame = binary_measurement();
+
+        framer.push(head.to_vec());
+        assert_eq!(framer.push(frame.clone()), (vec![frame], 1));
+        assert_eq!(
+            framer.push(MEASUREMENT.to_vec()),
+            (vec![MEASUREMENT.to_vec()], 0)
+        );
+    }
+
+    #[test]
+    fn json_braces_in_strings_do_not_count() {
+        let mut framer = JsonFramer::new();
+        let object = br#"{"note": "a } and a \" {"}"#;
+
+        assert_eq!(framer.push(object), vec![object.to_vec()]);
+    }
+}
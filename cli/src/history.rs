@@ -1,5 +1,5 @@
 #![allow(dead_code)]
-use crate::climate_data::{ClimateData, Timestamp};
+use crate::climate_data::{ClimateData, SensorFaults, Timestamp};
 use std::ops::{Add, Range};
 
 pub struct MaxSizedVector<T, const MAX_SIZE: usize> {
@@ -79,9 +79,56 @@ const HISTORY_SIZE: usize = 17280;
 /// .0 - timestamp, .1 - value
 type HistoryPoint = (Timestamp, f64);
 
+// sensors usually fail for a while, so only the changes of the faults are kept
+const FAULT_HISTORY_SIZE: usize = 1440;
+
+/// When the set of faulty sensors changed, `.1` are the faults from that moment on
+pub struct FaultHistory {
+    pub changes: MaxSizedVector<(Timestamp, SensorFaults), FAULT_HISTORY_SIZE>,
+}
+
+impl FaultHistory {
+    pub fn new() -> Self {
+        Self {
+            changes: MaxSizedVector::new(),
+        }
+    }
+
+    pub fn capture_faults(&mut self, timestamp: Timestamp, faults: SensorFaults) {
+        if self.current() != faults {
+            self.changes.push((timestamp, faults));
+        }
+    }
+
+    pub fn current(&self) -> SensorFaults {
+        self.changes
+            .as_slice()
+            .last()
+            .map(|(_, faults)| *faults)
+            .unwrap_or_default()
+    }
+
+    /// When the sensor started failing, `None` if it works now
+    pub fn failing_since(&self, sensor: SensorFaults) -> Option<Timestamp> {
+        let mut since = None;
+        for (timestamp, faults) in self.changes.as_slice().iter().rev() {
+            if !faults.contains(sensor) {
+                break;
+            }
+            since = Some(*timestamp);
+        }
+
+        since
+    }
+}
+
 pub struct History {
     time_window: [f64; 2],
     pub latest_climate_data: Option<ClimateData>,
+    /// What the device sent before the calibration profile corrected it, the device is
+    /// calibrated against its own readings
+    pub latest_raw: Option<ClimateData>,
+    pub raw_co2_history: MaxSizedVector<HistoryPoint, HISTORY_SIZE>,
     pub flat: MaxSizedVector<ClimateData, HISTORY_SIZE>,
     pub co2_history: MaxSizedVector<HistoryPoint, HISTORY_SIZE>,
     pub eco2_history: MaxSizedVector<HistoryPoint, HISTORY_SIZE>,
@@ -89,6 +136,7 @@ pub struct History {
     pub temperature_minmax: Option<Range<f64>>,
     pub pressure_history: MaxSizedVector<HistoryPoint, HISTORY_SIZE>,
     pub pressure_minmax: Option<Range<f64>>,
+    pub faults: FaultHistory,
 }
 
 impl History {
@@ -123,6 +171,8 @@ impl History {
         let now = chrono::offset::Local::now().timestamp_millis() as f64;
         Self {
             latest_climate_data: None,
+            latest_raw: None,
+            raw_co2_history: MaxSizedVector::new(),
             time_window: [now, now],
             flat: MaxSizedVector::new(),
             co2_history: MaxSizedVector::new(),
@@ -131,31 +181,115 @@ impl History {
             temperature_minmax: None,
             pressure_history: MaxSizedVector::new(),
             pressure_minmax: None,
+            faults: FaultHistory::new(),
         }
     }
 
-    pub fn capture_measurement(&mut self, climate_data: &ClimateData) {
+    /// `raw` is the same measurement before the calibration profile corrected it
+    pub fn capture_measurement(&mut self, climate_data: &ClimateData, raw: &ClimateData) {
         self.latest_climate_data = Some(*climate_data);
+        self.latest_raw = Some(*raw);
         let ts = climate_data.timestamp;
 
         self.flat.push(*climate_data);
-        self.temperature_history
-            .push((ts, climate_data.temperature as f64));
-        self.temperature_minmax = Some(Self::update_min_max_range(
-            climate_data.temperature as f64,
-            &self.temperature_minmax,
-        ));
-
-        if let Some(co2) = climate_data.co2 {
+        self.faults.capture_faults(ts, climate_data.error_flags);
+
+        // readings of a faulty sensor would only spoil the charts and their bounds
+        if climate_data.is_temperature_valid() {
+            self.temperature_history
+                .push((ts, climate_data.temperature as f64));
+            self.temperature_minmax = Some(Self::update_min_max_range(
+                climate_data.temperature as f64,
+                &self.temperature_minmax,
+            ));
+        }
+
+        if let Some(co2) = climate_data.co2.filter(|_| climate_data.is_co2_valid()) {
             self.co2_history.push((ts, co2 as f64));
         }
 
-        self.eco2_history.push((ts, climate_data.eco2 as f64));
-        self.pressure_history
-            .push((ts, climate_data.pressure as f64));
-        self.pressure_minmax = Some(Self::update_min_max_range(
-            climate_data.pressure as f64,
-            &self.pressure_minmax,
-        ));
+        if let Some(co2) = raw.co2.filter(|_| raw.is_co2_valid()) {
+            self.raw_co2_history.push((ts, co2 as f64));
+        }
+
+        if climate_data.is_eco2_valid() {
+            self.eco2_history.push((ts, climate_data.eco2 as f64));
+        }
+
+        if climate_data.is_pressure_valid() {
+            self.pressure_history
+                .push((ts, climate_data.pressure as f64));
+            self.pressure_minmax = Some(Self::update_min_max_range(
+                climate_data.pressure as f64,
+                &self.pressure_minmax,
+            ));
+        }
     }
-}
\ No newline at end of file
+}
+
+// the window sensor reports every 30 seconds, a state change can not happen more often
+const WINDOW_TIMELINE_SIZE: usize = 2880;
+
+/// Open/closed changes of the window sensor, `.1` is true when the window got closed
+pub struct WindowTimeline {
+    pub changes: MaxSizedVector<(Timestamp, bool), WINDOW_TIMELINE_SIZE>,
+    /// When the sensor reported last, the state itself may not have changed for hours
+    pub last_report: Option<Timestamp>,
+}
+
+impl WindowTimeline {
+    pub fn new() -> Self {
+        Self {
+            changes: MaxSizedVector::new(),
+            last_report: None,
+        }
+    }
+
+    pub fn capture_state(&mut self, is_closed: bool) {
+        let now = Timestamp::default();
+        self.last_report = Some(now);
+
+        if self.is_closed() != Some(is_closed) {
+            self.changes.push((now, is_closed));
+        }
+    }
+
+    pub fn is_closed(&self) -> Option<bool> {
+        self.changes
+            .as_slice()
+            .last()
+            .map(|(_, is_closed)| *is_closed)
+    }
+
+    /// When the window got into its current state
+    pub fn since(&self) -> Option<Timestamp> {
+        self.changes.as_slice().last().map(|(since, _)| *since)
+    }
+}
+
+// the battery level changes by a percent at most every few minutes
+const BATTERY_HISTORY_SIZE: usize = 1440;
+
+pub struct BatteryHistory {
+    pub levels: MaxSizedVector<HistoryPoint, BATTERY_HISTORY_SIZE>,
+}
+
+impl BatteryHistory {
+    pub fn new() -> Self {
+        Self {
+            levels: MaxSizedVector::new(),
+        }
+    }
+
+    pub fn latest(&self) -> Option<u8> {
+        self.levels.as_slice().last().map(|(_, level)| *level as u8)
+    }
+
+    /// Stores the level and returns the previous one
+    pub fn capture_level(&mut self, level: u8) -> Option<u8> {
+        let previous = self.latest();
+        self.levels.push((Timestamp::default(), level as f64));
+
+        previous
+    }
+}
@@ -1,119 +1,179 @@
+mod args;
+mod battery;
 mod ble_actions;
 mod config;
 mod history;
 mod tui_app;
-use climate_data::ClimateData;
-use crossterm::{
-    terminal::{disable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-    ExecutableCommand,
-};
-use history::History;
-use spinners::{Spinner, Spinners};
-use tui_app::TerminalUi;
-use uuid::Uuid;
+use args::{Args, CalibrateTarget, Command, ConfigAction};
+use clap::Parser;
+use config::settings::Settings;
+use error::{Error, Result};
+use sources::SourceKind;
 mod bluetooth;
-use config::*;
-use ratatui::{backend::CrosstermBackend, Terminal};
-use std::{
-    error::Error,
-    fmt::Display,
-    io::stdout,
-    str::FromStr,
-    sync::{Arc, Mutex, RwLock},
-};
-
-use crate::ble_actions::run_ble_mpsc;
+mod calibration;
+mod calibration_log;
 
 mod climate_data;
+mod commands;
+mod connection;
+mod correction;
+mod devices;
+mod error;
+mod framing;
 mod reactions;
+mod recorder;
+mod scan;
+mod sources;
+mod window_sensor;
+
+async fn print_adapters() -> Result<()> {
+    let adapters = bluetooth::list_adapters().await?;
+    if adapters.is_empty() {
+        println!("No Bluetooth adapters found");
+    }
+
+    for (index, info) in adapters.iter().enumerate() {
+        println!("{index}: {info}");
+    }
+
+    Ok(())
+}
 
-fn set_terminal_tab_title(climate_data: impl AsRef<str> + Display) {
-    use std::io::Write;
+fn run_config_command(action: &ConfigAction, settings: &Settings) -> Result<()> {
+    let path = Settings::path()
+        .ok_or_else(|| Error::Config("can not find the config directory".to_string()))?;
+
+    match action {
+        ConfigAction::Show => {
+            if path.exists() {
+                println!("# {}", path.display());
+            } else {
+                println!("# {} does not exist, showing the defaults", path.display());
+            }
+            print!("{}", settings.to_full_toml()?);
+        }
+        ConfigAction::Init { force } => {
+            if path.exists() && !force {
+                return Err(Error::Config(format!(
+                    "{} already exists, pass --force to rewrite it",
+                    path.display()
+                )));
+            }
+
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(&path, settings.to_full_toml()?)?;
+            println!("Wrote {}", path.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Fails early with a readable message instead of leaving the user at the spinner
+/// while the device tasks can not even start scanning
+async fn check_adapter() -> bool {
+    match bluetooth::check_adapter().await {
+        Ok(info) => {
+            tracing::info!("Using Bluetooth adapter {info}");
+            true
+        }
+        Err(e) => {
+            eprintln!("Bluetooth is not available: {e}");
+            if matches!(e, Error::Adapter(_)) {
+                eprintln!("Pick another adapter with --adapter <INDEX|NAME> or the `adapter` option in the config file, see `co2nsole scan --adapters`.");
+            } else {
+                eprintln!("Make sure Bluetooth is turned on and the bluetooth service is running.");
+            }
+            eprintln!("To read the sensor without Bluetooth connect it over USB and use --serial.");
+            false
+        }
+    }
+}
 
-    print!("\x1B]0;{}\x07", climate_data);
-    if let Err(e) = std::io::stdout().flush() {
-        tracing::error!("Failed to update title of the console: {:?}", e);
+/// The source the command reads from, exits when it needs Bluetooth which is not available
+async fn connectable_source(args: &Args, settings: &Settings) -> SourceKind {
+    let kind = args.source_kind(settings);
+    if kind.uses_bluetooth() && !check_adapter().await {
+        std::process::exit(1);
     }
+
+    kind
 }
 
 #[tokio::main()]
-async fn main() -> Result<(), Box<dyn Error>> {
+async fn main() -> Result<()> {
+    let args = Args::parse();
     let file_appender = tracing_appender::rolling::hourly("/tmp/co2nsole", "cli.log");
     let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
 
     tracing_subscriber::fmt()
         .with_writer(non_blocking)
-        .with_max_level(tracing::Level::TRACE)
+        .with_max_level(args.log_level)
         .pretty()
         .init();
 
-    let backend = CrosstermBackend::new(stdout());
-    let history = Arc::new(RwLock::new(History::new()));
-    let terminal = Arc::new(Mutex::new(Terminal::new(backend)?));
-    let app = Arc::new(TerminalUi::new(Arc::clone(&history))?);
-
-    loop {
-        let mut spinner_stopped = false;
-        let mut spinner = Spinner::new(Spinners::Pong, "Connecting to sensor".to_owned());
-        tracing::debug!("Looking for a sensor...");
-        set_terminal_tab_title("Connecting to a sensor...");
-
-        if let Ok(connection) = bluetooth::connect_to(
-            &BLE_MAIN_SERVICE_LOCAL_NAME,
-            Uuid::from_str(&BLE_MAIN_SENSOR_SERVICE)?,
-        )
-        .await
-        {
-            stdout().execute(EnterAlternateScreen)?;
-            crossterm::terminal::enable_raw_mode()?;
-            let (tx, rx) = tokio::sync::mpsc::channel(100);
-
-            // Exit of the app can happen only from the event poller:
-            TerminalUi::start_event_polling(Arc::clone(&app), tx, terminal.clone());
-
-            let ble_action_handler = run_ble_mpsc(&connection, rx);
-            let ble_subscription = connection.subscribe(
-                Uuid::from_str(&BLE_MAIN_SENSOR_STREAM_CHAR)?,
-                |data: ClimateData| {
-                    let terminal = &mut terminal.lock().unwrap();
-                    tracing::debug!("New climate data: {:?}", data);
-                    if !spinner_stopped {
-                        spinner.stop();
-                        terminal.clear().unwrap();
-                        spinner_stopped = true
-                    }
-
-                    set_terminal_tab_title(format!(
-                        "T {:.2}°C; CO2 {} ppm; H {}%",
-                        data.temperature,
-                        data.co2.unwrap_or(400),
-                        data.humidity.round()
-                    ));
-
-                    {
-                        history.write().unwrap().capture_measurement(&data);
-                    }
-
-                    app.draw(terminal);
-
-                    if cfg!(debug_assertions) {
-                        reactions::run_reactions(history.read().unwrap().flat.as_slice());
-                    }
-                },
-            );
-
-            match tokio::try_join!(ble_action_handler, ble_subscription) {
-                Ok(_) => (),
-                Err(e) => {
-                    tracing::error!("Error in BLE connection: {:?}", e);
-                }
-            }
+    if let Some(path) = &args.config {
+        Settings::use_path(path.clone());
+    }
+    let mut settings = Settings::load()?;
+    config::runtime::init(&settings);
 
-            connection.disconnect_with_timeout().await;
-            stdout().execute(LeaveAlternateScreen)?;
-            disable_raw_mode()?;
-        }
+    if let Some(adapter) = args.adapter.as_ref().or(settings.adapter.as_ref()) {
+        bluetooth::prefer_adapter(adapter.clone());
+    }
 
-        terminal.lock().unwrap().clear()?;
+    let record = args.record.as_deref();
+    match args.command.as_ref().unwrap_or(&Command::Tui) {
+        Command::Config { action } => run_config_command(action, &settings),
+        Command::Scan { adapters: true } => print_adapters().await,
+        Command::Scan { adapters: false } => {
+            if !check_adapter().await {
+                std::process::exit(1);
+            }
+            scan::run(&mut settings).await.map(|_| ())
+        }
+        Command::Calibrate {
+            target: CalibrateTarget::Log,
+        } => commands::print_calibration_log(&settings),
+        Command::Calibrate { target } => {
+            let kind = connectable_source(&args, &settings).await;
+            commands::calibrate(kind, settings, record, target).await
+        }
+        Command::Tui => {
+            let kind = connectable_source(&args, &settings).await;
+            commands::tui(kind, settings, record).await
+        }
+        Command::Watch { json } => {
+            let kind = connectable_source(&args, &settings).await;
+            commands::watch(kind, settings, record, *json).await
+        }
+        Command::Export {
+            format,
+            output,
+            count,
+            duration,
+        } => {
+            let kind = connectable_source(&args, &settings).await;
+            commands::export(
+                kind,
+                settings,
+                record,
+                *format,
+                output.as_ref(),
+                *count,
+                *duration,
+            )
+            .await
+        }
+        Command::Daemon => {
+            let kind = connectable_source(&args, &settings).await;
+            commands::daemon(kind, settings, record).await
+        }
+        Command::Status { json, timeout } => {
+            let kind = connectable_source(&args, &settings).await;
+            commands::status(kind, settings, record, *json, *timeout).await
+        }
     }
 }
@@ -1,8 +1,7 @@
-use crate::climate_data::ClimateData;
+use crate::{climate_data::ClimateData, devices::Devices, error::Result};
 use async_trait::async_trait;
 use std::cmp::Ordering;
-use std::error::Error;
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Trend {
@@ -24,7 +23,7 @@ pub trait DataReaction<T: PartialOrd> {
         false
     }
 
-    async fn run() -> Result<(), Box<dyn Error>>;
+    async fn run(devices: Arc<Devices>) -> Result<()>;
 
     fn validate(values: &[ClimateData]) -> bool {
         let self_type_name = std::any::type_name::<Self>();
@@ -57,7 +56,7 @@ pub trait DataReaction<T: PartialOrd> {
             return false;
         }
 
-        if values.len() % period_size as usize != 0 {
+        if !values.len().is_multiple_of(period_size as usize) {
             tracing::debug!(
                 ?self_type_name,
                 "reaction not needed – waiting for next period"
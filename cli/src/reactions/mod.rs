@@ -1,16 +1,20 @@
 mod window;
-pub use window::*;
+use crate::window_sensor::WindowState;
 
 use crate::reactions::data_reaction::DataReaction;
 mod data_reaction;
 
 macro_rules! define_reactions {
     ($($x: ty), +) => {
-        pub fn run_reactions(data: &[crate::climate_data::ClimateData]) -> () {
+        pub fn run_reactions(
+            data: &[crate::climate_data::ClimateData],
+            devices: &std::sync::Arc<crate::devices::Devices>,
+        ) -> () {
           $({
             if <$x>::validate(data) {
+              let devices = std::sync::Arc::clone(devices);
               tokio::spawn(async move {
-                if let Err(e) = <$x>::run().await {
+                if let Err(e) = <$x>::run(devices).await {
                   tracing::error!("Error running reaction {}: {}", std::any::type_name::<$x>(), e)
                 }
               });
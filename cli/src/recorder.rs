@@ -0,0 +1,55 @@
+use crate::climate_data::ClimateData;
+use serde::Serialize;
+use std::{
+    fs::OpenOptions,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// A recorded line is the measurement itself plus the device it came from, the replay source
+/// plays every device back separately. The measurement is the corrected one, the device
+/// reading is kept next to it when a calibration profile changed it.
+#[derive(Serialize)]
+struct RecordedMeasurement<'a> {
+    device: &'a str,
+    #[serde(flatten)]
+    data: &'a ClimateData,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    raw: Option<&'a ClimateData>,
+}
+
+/// Appends every received measurement as a JSON line, the file can be played back later
+/// with the replay source.
+pub struct Recorder {
+    writer: Box<dyn Write + Send>,
+}
+
+impl Recorder {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self {
+            writer: Box::new(BufWriter::new(file)),
+        })
+    }
+
+    /// The same lines on the standard output, to be piped into other tools
+    pub fn stdout() -> Self {
+        Self {
+            writer: Box::new(io::stdout()),
+        }
+    }
+
+    pub fn record(
+        &mut self,
+        device: &str,
+        data: &ClimateData,
+        raw: Option<&ClimateData>,
+    ) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, &RecordedMeasurement { device, data, raw })?;
+        self.writer.write_all(b"\n")?;
+        // flush every line so the session survives a crash of the CLI, which is exactly
+        // the situation we want to reproduce later
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,166 @@
+use crate::{
+    bluetooth::{self, ScannedPeripheral},
+    config::{self, settings::Settings},
+    error::Result,
+};
+use tokio::io::{AsyncBufReadExt, BufReader, Stdin};
+use uuid::Uuid;
+
+fn characteristic_name(uuid: &Uuid) -> &'static str {
+    let ble = config::ble();
+    let known = [
+        (ble.stream_char, "measurements stream"),
+        (ble.co2_calibration_char, "CO2 calibration"),
+        (ble.temperature_calibration_char, "temperature calibration"),
+        (ble.humidity_calibration_char, "humidity calibration"),
+        (ble.window_read_char, "window state"),
+        (ble.window_service, "window state"),
+    ];
+
+    known
+        .iter()
+        .find(|(known_uuid, _)| known_uuid == uuid)
+        .map(|(_, name)| *name)
+        .unwrap_or("")
+}
+
+fn is_main_sensor(peripheral: &ScannedPeripheral, main_service: &Uuid) -> bool {
+    peripheral.services.contains(main_service)
+        || (peripheral
+            .local_name
+            .contains(&config::ble().main_local_name)
+            && !peripheral
+                .local_name
+                .contains(&config::ble().window_local_name))
+}
+
+fn print_peripheral(index: usize, peripheral: &ScannedPeripheral, main_service: &Uuid) {
+    println!(
+        "{:>3}  {:<18} {:<20} {:>8}  {:<7} {}",
+        index + 1,
+        peripheral.local_name,
+        peripheral.address,
+        peripheral
+            .rssi
+            .map(|rssi| format!("{rssi} dBm"))
+            .unwrap_or("n/a".to_string()),
+        if is_main_sensor(peripheral, main_service) {
+            "sensor"
+        } else {
+            "window"
+        },
+        if peripheral.is_connected { "yes" } else { "no" },
+    );
+
+    match &peripheral.characteristics {
+        Ok(characteristics) => {
+            for (uuid, properties) in characteristics {
+                let properties = properties
+                    .iter_names()
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>()
+                    .join(" | ");
+
+                println!("       {uuid} [{properties}] {}", characteristic_name(uuid));
+            }
+        }
+        Err(e) => println!("       could not read characteristics: {e}"),
+    }
+}
+
+/// Reads the answer from `input`, which is shared by all the prompts so the lines it
+/// already buffered (e.g. piped in at once) are not lost
+async fn prompt(input: &mut BufReader<Stdin>, message: &str) -> Result<String> {
+    use std::io::Write;
+
+    print!("{message}");
+    std::io::stdout().flush()?;
+
+    let mut line = String::new();
+    input.read_line(&mut line).await?;
+
+    Ok(line.trim().to_string())
+}
+
+/// Asks for an alias of the picked sensor and remembers it as the default device
+async fn remember(
+    input: &mut BufReader<Stdin>,
+    peripheral: &ScannedPeripheral,
+    settings: &mut Settings,
+) -> Result<()> {
+    let suggested_alias = settings
+        .alias_of(&peripheral.address)
+        .unwrap_or(&peripheral.local_name)
+        .to_string();
+
+    let alias = prompt(input, &format!("Name this sensor [{suggested_alias}]: ")).await?;
+    let alias = if alias.is_empty() {
+        suggested_alias
+    } else {
+        alias
+    };
+
+    settings.remember_device(alias.clone(), peripheral.address.clone());
+    settings.save()?;
+    println!("Saved {alias} as the default sensor, next time the CLI will connect to it directly");
+
+    Ok(())
+}
+
+/// Prints every co2nsole in range and lets the user pick and remember a main sensor to
+/// connect to. Returns the address of the picked peripheral.
+pub async fn run(settings: &mut Settings) -> Result<Option<String>> {
+    let ble = config::ble();
+    let main_service = ble.main_service;
+    let window_service = ble.window_service;
+
+    println!("Scanning for co2nsole devices...");
+    let peripherals =
+        bluetooth::scan_peripherals(&ble.main_local_name, &[main_service, window_service]).await?;
+
+    if peripherals.is_empty() {
+        println!("No co2nsole devices found. Make sure the device is powered on, in range and that your terminal is allowed to use Bluetooth.");
+        return Ok(None);
+    }
+
+    println!(
+        "\n{:>3}  {:<18} {:<20} {:>8}  {:<7} CONNECTED",
+        "#", "NAME", "ADDRESS", "RSSI", "KIND"
+    );
+    for (index, peripheral) in peripherals.iter().enumerate() {
+        print_peripheral(index, peripheral, &main_service);
+        if let Some(alias) = settings.alias_of(&peripheral.address) {
+            println!("       remembered as {alias}");
+        }
+    }
+    println!();
+
+    let mut input = BufReader::new(tokio::io::stdin());
+    loop {
+        let answer = prompt(
+            &mut input,
+            &format!(
+                "Select a sensor to connect to [1-{}] or press Enter to exit: ",
+                peripherals.len()
+            ),
+        )
+        .await?;
+
+        if answer.is_empty() {
+            return Ok(None);
+        }
+
+        match answer
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| peripherals.get(index.wrapping_sub(1)))
+        {
+            Some(peripheral) if is_main_sensor(peripheral, &main_service) => {
+                remember(&mut input, peripheral, settings).await?;
+                return Ok(Some(peripheral.address.clone()));
+            }
+            Some(_) => println!("Window sensors can not be displayed on the dashboard"),
+            None => println!("There is no device number {answer}"),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use super::ClimateSource;
+use crate::{
+    battery,
+    ble_actions::{read_calibration, run_ble_mpsc, BleAction},
+    bluetooth::{self, Connection, LinkStats},
+    calibration::CalibrationState,
+    climate_data::ClimateData,
+    config,
+    connection::StateListener,
+    error::Result,
+};
+use async_trait::async_trait;
+use btleplug::api::Peripheral;
+use tokio::sync::mpsc;
+
+pub async fn connect(
+    address: Option<&str>,
+    on_state: &StateListener,
+) -> Result<Box<dyn ClimateSource>> {
+    let service_uuid = config::ble().main_service;
+
+    Ok(match address {
+        Some(address) => {
+            Box::new(bluetooth::connect_to_address(address, service_uuid, on_state).await?)
+        }
+        None => Box::new(
+            bluetooth::connect_to(&config::ble().main_local_name, service_uuid, on_state).await?,
+        ),
+    })
+}
+
+#[async_trait]
+impl<TPeripheral: Peripheral + 'static> ClimateSource for Connection<TPeripheral> {
+    fn describe(&self) -> String {
+        format!("BLE {} ({})", config::ble().main_local_name, self.address())
+    }
+
+    async fn subscribe(
+        &self,
+        on_data: &mut (dyn FnMut(ClimateData) + Send),
+        link: &LinkStats,
+    ) -> Result<()> {
+        Connection::subscribe(self, config::ble().stream_char, link, on_data).await
+    }
+
+    async fn watch_battery(&self, on_level: &mut (dyn FnMut(u8) + Send)) -> Result<()> {
+        battery::watch(self, on_level).await
+    }
+
+    async fn read_calibration(&self) -> Result<Option<CalibrationState>> {
+        read_calibration(self).await.map(Some)
+    }
+
+    async fn run_actions(&self, actions: mpsc::Receiver<BleAction>) -> Result<()> {
+        run_ble_mpsc(self, actions).await
+    }
+
+    async fn disconnect(&self) {
+        self.disconnect_with_timeout().await
+    }
+}
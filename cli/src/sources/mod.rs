@@ -0,0 +1,113 @@
+mod ble;
+mod replay;
+mod serial;
+mod simulator;
+
+pub use replay::{recorded_devices, ReplaySpeed};
+pub use simulator::SimulatorOptions;
+
+use crate::{
+    ble_actions::BleAction, bluetooth::LinkStats, calibration::CalibrationState,
+    climate_data::ClimateData, connection::StateListener, error::Result,
+};
+use async_trait::async_trait;
+use std::{fmt::Display, path::PathBuf};
+use tokio::sync::mpsc;
+
+/// A device or a stream that produces climate measurements. The TUI, history and reactions
+/// only talk to the source through this trait so they do not depend on a live BLE peripheral.
+#[async_trait]
+pub trait ClimateSource: Send + Sync {
+    /// Human readable description of the source used for logs and the terminal title
+    fn describe(&self) -> String;
+
+    /// Streams decoded climate data into `on_data` until the source is exhausted or fails.
+    /// Only a source with an end (e.g. a recording) returns `Ok`, a live device never runs
+    /// out of measurements so it always ends with an error.
+    async fn subscribe(
+        &self,
+        on_data: &mut (dyn FnMut(ClimateData) + Send),
+        link: &LinkStats,
+    ) -> Result<()>;
+
+    /// Reports the battery level of a battery powered device, never ends for the sources
+    /// without a battery
+    async fn watch_battery(&self, _on_level: &mut (dyn FnMut(u8) + Send)) -> Result<()> {
+        std::future::pending().await
+    }
+
+    /// Reads the calibration state right after connecting, `None` for the sources which can
+    /// not be calibrated
+    async fn read_calibration(&self) -> Result<Option<CalibrationState>> {
+        Ok(None)
+    }
+
+    /// Handles control commands coming from the UI until the channel is closed
+    /// or [`BleAction::Stop`] is received.
+    async fn run_actions(&self, actions: mpsc::Receiver<BleAction>) -> Result<()>;
+
+    /// Releases the underlying device, never fails
+    async fn disconnect(&self);
+}
+
+/// Which kind of source the CLI should read climate data from
+#[derive(Debug, Clone, Default)]
+pub enum SourceKind {
+    /// Discover every co2nsole in range, or connect to the given peripheral address only
+    #[default]
+    Ble,
+    BleDevice {
+        address: String,
+    },
+    Serial {
+        path: String,
+        baud_rate: u32,
+    },
+    Replay {
+        path: PathBuf,
+        speed: ReplaySpeed,
+        /// Plays back the measurements of this recorded device only, `None` for the lines
+        /// recorded without a device
+        device: Option<String>,
+    },
+    Simulator(SimulatorOptions),
+}
+
+impl SourceKind {
+    pub fn uses_bluetooth(&self) -> bool {
+        matches!(self, SourceKind::Ble | SourceKind::BleDevice { .. })
+    }
+}
+
+/// Opens the source, reporting the progress of the slow BLE connection to `on_state`
+pub async fn connect(
+    kind: &SourceKind,
+    on_state: &StateListener,
+) -> Result<Box<dyn ClimateSource>> {
+    match kind {
+        SourceKind::Ble => ble::connect(None, on_state).await,
+        SourceKind::BleDevice { address } => ble::connect(Some(address), on_state).await,
+        SourceKind::Serial { path, baud_rate } => serial::connect(path, *baud_rate).await,
+        SourceKind::Replay {
+            path,
+            speed,
+            device,
+        } => replay::connect(path, *speed, device.clone()).await,
+        SourceKind::Simulator(options) => simulator::connect(*options).await,
+    }
+}
+
+impl Display for SourceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceKind::Ble => write!(f, "ble"),
+            SourceKind::BleDevice { address } => write!(f, "{address}"),
+            SourceKind::Serial { path, .. } => write!(f, "{path}"),
+            SourceKind::Replay { path, .. } => match path.file_name() {
+                Some(file_name) => write!(f, "{}", file_name.to_string_lossy()),
+                None => write!(f, "{}", path.display()),
+            },
+            SourceKind::Simulator(_) => write!(f, "simulator"),
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use super::ClimateSource;
+use crate::{
+    ble_actions::BleAction,
+    bluetooth::LinkStats,
+    climate_data::ClimateData,
+    error::{Error, Result},
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+use tokio::{
+    fs::File,
+    io::{AsyncBufReadExt, BufReader},
+    sync::mpsc,
+    time::sleep,
+};
+
+/// How fast a recorded session is played back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    /// Multiplier of the original pace, `1.0` replays in real time
+    Times(f64),
+    /// Ignore the recorded timestamps and push everything as fast as possible
+    Max,
+}
+
+impl Default for ReplaySpeed {
+    fn default() -> Self {
+        Self::Times(1.0)
+    }
+}
+
+impl FromStr for ReplaySpeed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("max") {
+            return Ok(Self::Max);
+        }
+
+        match s.trim_end_matches(['x', 'X']).parse::<f64>() {
+            Ok(times) if times > 0.0 && times.is_finite() => Ok(Self::Times(times)),
+            _ => Err(format!(
+                "invalid replay speed {s:?}, expected a positive multiplier like 1x, 10x or max"
+            )),
+        }
+    }
+}
+
+impl Display for ReplaySpeed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Times(times) => write!(f, "{times}x"),
+            Self::Max => write!(f, "max"),
+        }
+    }
+}
+
+/// A line written by [`crate::recorder::Recorder`], the recordings made before the CLI
+/// supported several devices have the measurement only
+#[derive(Deserialize)]
+struct RecordedLine {
+    #[serde(default)]
+    device: Option<String>,
+    /// What the device sent when a calibration profile corrected it
+    #[serde(default)]
+    raw: Option<ClimateData>,
+    #[serde(flatten)]
+    data: ClimateData,
+}
+
+impl RecordedLine {
+    /// The device reading, so the calibration profile is applied once again on the way in
+    /// rather than on top of the recorded correction
+    fn measurement(self) -> ClimateData {
+        self.raw.unwrap_or(self.data)
+    }
+}
+
+/// Every device of the recording in the order they first appear
+pub async fn recorded_devices(path: &Path) -> Result<Vec<Option<String>>> {
+    let mut lines = BufReader::new(File::open(path).await?).lines();
+    let mut devices = Vec::new();
+
+    while let Some(line) = lines.next_line().await? {
+        if let Ok(RecordedLine { device, .. }) = serde_json::from_str(&line) {
+            if !devices.contains(&device) {
+                devices.push(device);
+            }
+        }
+    }
+
+    Ok(devices)
+}
+
+pub struct ReplaySource {
+    path: PathBuf,
+    speed: ReplaySpeed,
+    device: Option<String>,
+}
+
+pub async fn connect(
+    path: &Path,
+    speed: ReplaySpeed,
+    device: Option<String>,
+) -> Result<Box<dyn ClimateSource>> {
+    if !tokio::fs::try_exists(path).await? {
+        return Err(Error::RecordingNotFound(path.to_path_buf()));
+    }
+
+    Ok(Box::new(ReplaySource {
+        path: path.to_path_buf(),
+        speed,
+        device,
+    }))
+}
+
+#[async_trait]
+impl ClimateSource for ReplaySource {
+    fn describe(&self) -> String {
+        match &self.device {
+            Some(device) => format!(
+                "replay of {device} from {} at {}",
+                self.path.display(),
+                self.speed
+            ),
+            None => format!("replay {} at {}", self.path.display(), self.speed),
+        }
+    }
+
+    async fn subscribe(
+        &self,
+        on_data: &mut (dyn FnMut(ClimateData) + Send),
+        link: &LinkStats,
+    ) -> Result<()> {
+        let mut lines = BufReader::new(File::open(&self.path).await?).lines();
+        let mut previous_timestamp = None;
+
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let line = match serde_json::from_str::<RecordedLine>(&line) {
+                Ok(line) => line,
+                Err(e) => {
+                    link.record_decode_failure();
+                    tracing::error!("Skipping malformed recorded measurement {line:?}: {e}");
+                    continue;
+                }
+            };
+
+            // the lines of the other devices are waited for as well, so the replays of all
+            // the devices of the recording keep their original order
+            let timestamp = line.data.timestamp.as_f64();
+            match (self.speed, previous_timestamp) {
+                (ReplaySpeed::Times(times), Some(previous)) => {
+                    let delay_ms = (timestamp - previous) / times;
+                    if delay_ms > 0.0 {
+                        sleep(Duration::from_secs_f64(delay_ms / 1000.0)).await;
+                    }
+                }
+                // let the ui thread breathe between the measurements
+                (ReplaySpeed::Max, _) => tokio::task::yield_now().await,
+                _ => (),
+            }
+
+            previous_timestamp = Some(timestamp);
+            if line.device != self.device {
+                continue;
+            }
+
+            link.record_notification();
+            on_data(line.measurement());
+        }
+
+        tracing::info!("Replay of {} finished", self.path.display());
+        Ok(())
+    }
+
+    async fn run_actions(&self, mut actions: mpsc::Receiver<BleAction>) -> Result<()> {
+        while let Some(action) = actions.recv().await {
+            match action {
+                BleAction::Stop => {
+                    tracing::info!("Stopping replay");
+                    break;
+                }
+                _ => tracing::warn!("Replayed session can not be calibrated"),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn disconnect(&self) {}
+}
@@ -0,0 +1,144 @@
+use super::ClimateSource;
+use crate::{
+    ble_actions::BleAction,
+    bluetooth::{FromBleData, LinkStats},
+    climate_data::ClimateData,
+    error::{Error, Result},
+    framing::JsonFramer,
+};
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::{
+    io::AsyncReadExt,
+    sync::{mpsc, Mutex},
+    time::timeout,
+};
+use tokio_serial::{SerialPortBuilderExt, SerialStream};
+
+/// The firmware sends a measurement every 2 seconds, so silence for this long means the
+/// device was unplugged or rebooted into a state where it doesn't print anything.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct SerialSource {
+    path: String,
+    port: Mutex<SerialStream>,
+}
+
+pub async fn connect(path: &str, baud_rate: u32) -> Result<Box<dyn ClimateSource>> {
+    tracing::debug!("Opening serial port {path} at {baud_rate} baud");
+    let port = tokio_serial::new(path, baud_rate).open_native_async()?;
+
+    Ok(Box::new(SerialSource {
+        path: path.to_string(),
+        port: Mutex::new(port),
+    }))
+}
+
+#[async_trait]
+impl ClimateSource for SerialSource {
+    fn describe(&self) -> String {
+        format!("serial {}", self.path)
+    }
+
+    async fn subscribe(
+        &self,
+        on_data: &mut (dyn FnMut(ClimateData) + Send),
+        link: &LinkStats,
+    ) -> Result<()> {
+        let mut port = self.port.lock().await;
+        let mut framer = JsonFramer::new();
+        let mut buffer = [0u8; 512];
+
+        loop {
+            let read = timeout(TIMEOUT, port.read(&mut buffer))
+                .await
+                .map_err(|_| Error::NoData(TIMEOUT))??;
+
+            if read == 0 {
+                return Err(Error::SourceClosed(format!("Serial port {}", self.path)));
+            }
+
+            for frame in framer.push(&buffer[..read]) {
+                link.record_notification();
+                match ClimateData::from_bytes(frame) {
+                    Ok(data) => on_data(data),
+                    Err(e) => {
+                        link.record_decode_failure();
+                        tracing::error!("Error decoding data from serial port {}", e)
+                    }
+                }
+            }
+        }
+    }
+
+    async fn run_actions(&self, mut actions: mpsc::Receiver<BleAction>) -> Result<()> {
+        while let Some(action) = actions.recv().await {
+            match action {
+                BleAction::Stop => {
+                    tracing::info!("Stopping serial actions");
+                    break;
+                }
+                _ => tracing::warn!("Serial port source does not support calibration commands"),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn disconnect(&self) {
+        tracing::debug!("Closing serial port {}", self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn reads_measurements_over_a_pty() {
+        let (mut device, port) = SerialStream::pair().expect("failed to open a pty pair");
+        let source = SerialSource {
+            path: "pty".to_string(),
+            port: Mutex::new(port),
+        };
+        let link = LinkStats::default();
+        let (sender, mut received) = mpsc::unbounded_channel();
+        let mut on_data = |data| sender.send(data).unwrap();
+
+        let device_output = async {
+            device
+                .write_all(b"Sensors ready\r\n{\"temperature\": 21.5, \"pressure\": 1009.9, ")
+                .await?;
+            device.flush().await?;
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            device
+                .write_all(b"\"humidity\": 40.0, \"light\": null, \"co2\": 650, \"eco2\": 400, \"etvoc\": 3}\r\n")
+                .await?;
+            device
+                .write_all(b"{\"temperature\": -4.0, \"pressure\": 990.1, \"humidity\": 80.5, \"light\": 2, \"co2\": null, \"eco2\": 0, \"etvoc\": 0, \"error_flags\": 1}")
+                .await?;
+            device.flush().await?;
+
+            Ok::<_, std::io::Error>((received.recv().await, received.recv().await))
+        };
+
+        let (first, second) = tokio::select! {
+            result = source.subscribe(&mut on_data, &link) => panic!("subscribe ended: {result:?}"),
+            output = timeout(Duration::from_secs(5), device_output) => output.expect("no measurement over the pty").unwrap(),
+        };
+
+        let first = first.unwrap();
+        assert_eq!(first.temperature, 21.5);
+        assert_eq!(first.co2, Some(650));
+        assert_eq!(first.light, None);
+
+        let second = second.unwrap();
+        assert_eq!(second.temperature, -4.0);
+        assert_eq!(second.co2, None);
+        assert!(!second.is_co2_valid());
+
+        assert_eq!(link.snapshot().notifications, 2);
+        assert_eq!(link.snapshot().decode_failures, 0);
+    }
+}
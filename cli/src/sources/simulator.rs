@@ -0,0 +1,312 @@
+use super::ClimateSource;
+use crate::{
+    ble_actions::BleAction,
+    bluetooth::LinkStats,
+    calibration::{Adjustment, CalibrationState},
+    climate_data::{ClimateData, SensorFaults, Timestamp},
+    error::Result,
+};
+use async_trait::async_trait;
+use chrono::{NaiveTime, Timelike};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{f64::consts::PI, sync::Mutex, time::Duration};
+use tokio::{sync::mpsc, time::interval};
+
+/// Same cadence the firmware uses to notify about new measurements
+const TICK: Duration = Duration::from_secs(2);
+const OUTDOOR_CO2: f64 = 420.0;
+/// CO2 produced by a single person in a small office, ppm per second
+const CO2_PER_PERSON: f64 = 0.25;
+/// Share of the CO2 excess removed per second with closed and opened windows
+const LEAK_RATE: f64 = 0.0002;
+const VENTILATION_RATE: f64 = 0.003;
+/// The simulated battery loses a percent this often
+const BATTERY_DRAIN_INTERVAL: Duration = Duration::from_secs(120);
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SimulatorOptions {
+    /// Makes the sequence of simulated events reproducible between runs
+    pub seed: Option<u64>,
+    /// Randomly break the CO2 sensor and drop measurements
+    pub faults: bool,
+    /// Time of the day the simulation starts at, drives the occupancy and the daylight
+    pub start: Option<NaiveTime>,
+}
+
+impl SimulatorOptions {
+    /// A seeded run starts at the beginning of a working day rather than the current time,
+    /// otherwise two runs with the same seed would differ by the time of day they ran at
+    fn start(&self) -> NaiveTime {
+        match (self.start, self.seed) {
+            (Some(start), _) => start,
+            (None, Some(_)) => NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            (None, None) => chrono::Local::now().time(),
+        }
+    }
+}
+
+struct SimulatorState {
+    rng: StdRng,
+    start: NaiveTime,
+    /// Measurements generated so far, the simulated clock advances by a [`TICK`] with each
+    ticks: u64,
+    occupants: u32,
+    window_open_ticks: u32,
+    co2: f64,
+    temperature: f64,
+    humidity: f64,
+    pressure: f64,
+    co2_fault_ticks: u32,
+    dropout_ticks: u32,
+    rssi: f64,
+}
+
+pub struct Simulator {
+    options: SimulatorOptions,
+    state: Mutex<SimulatorState>,
+}
+
+pub async fn connect(options: SimulatorOptions) -> Result<Box<dyn ClimateSource>> {
+    let rng = match options.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    Ok(Box::new(Simulator {
+        options,
+        state: Mutex::new(SimulatorState {
+            rng,
+            start: options.start(),
+            ticks: 0,
+            occupants: 0,
+            window_open_ticks: 0,
+            co2: 550.0,
+            temperature: 21.5,
+            humidity: 45.0,
+            pressure: 1013.25,
+            co2_fault_ticks: 0,
+            dropout_ticks: 0,
+            rssi: -60.0,
+        }),
+    }))
+}
+
+/// Share of the day light reaching the sensor, 0 at night and 1 at the noon
+fn daylight(hour: f64) -> f64 {
+    if !(6.0..=21.0).contains(&hour) {
+        return 0.0;
+    }
+
+    (PI * (hour - 6.0) / 15.0).sin()
+}
+
+impl SimulatorState {
+    /// Hour of the simulated day with the minutes as the fraction
+    fn hour(&self) -> f64 {
+        let elapsed = self.ticks * TICK.as_secs();
+        let seconds = (self.start.num_seconds_from_midnight() as u64 + elapsed) % SECONDS_PER_DAY;
+
+        seconds as f64 / 3600.0
+    }
+
+    fn update_occupancy(&mut self, hour: f64) {
+        let working_hours = (9.0..18.0).contains(&hour);
+        let (arrive, leave) = if working_hours {
+            (0.004, 0.002)
+        } else {
+            (0.0005, 0.01)
+        };
+
+        if self.occupants < 4 && self.rng.gen_bool(arrive) {
+            self.occupants += 1;
+            tracing::debug!("Simulated person arrived, {} in the room", self.occupants);
+        } else if self.occupants > 0 && self.rng.gen_bool(leave) {
+            self.occupants -= 1;
+            tracing::debug!("Simulated person left, {} in the room", self.occupants);
+        }
+    }
+
+    fn update_ventilation(&mut self) {
+        if self.window_open_ticks > 0 {
+            self.window_open_ticks -= 1;
+        } else if self.co2 > 1200.0 && self.rng.gen_bool(0.01) {
+            // somebody finally noticed the stuffy air, keep the window open for 5-15 minutes
+            self.window_open_ticks = self.rng.gen_range(150..450);
+            tracing::debug!("Simulated window opened");
+        }
+    }
+
+    fn next(&mut self, options: &SimulatorOptions) -> Option<ClimateData> {
+        let hour = self.hour();
+        let dt = TICK.as_secs_f64();
+        self.ticks += 1;
+
+        self.update_occupancy(hour);
+        self.update_ventilation();
+        let window_open = self.window_open_ticks > 0;
+        let occupants = self.occupants as f64;
+
+        let exchange_rate = if window_open {
+            VENTILATION_RATE
+        } else {
+            LEAK_RATE
+        };
+        self.co2 += (occupants * CO2_PER_PERSON - (self.co2 - OUTDOOR_CO2) * exchange_rate) * dt;
+        self.co2 = self.co2.max(OUTDOOR_CO2);
+
+        let target_temperature = if window_open {
+            16.0
+        } else {
+            21.0 + 1.5 * daylight(hour) + 0.3 * occupants
+        };
+        self.temperature += (target_temperature - self.temperature) * 0.002 * dt;
+
+        let target_humidity = if window_open {
+            55.0
+        } else {
+            42.0 + 2.0 * occupants
+        };
+        self.humidity += (target_humidity - self.humidity) * 0.002 * dt;
+        self.pressure = (self.pressure + self.rng.gen_range(-0.05..0.05)).clamp(990.0, 1030.0);
+        self.rssi = (self.rssi + self.rng.gen_range(-1.0..1.0)).clamp(-90.0, -40.0);
+
+        if options.faults {
+            if self.dropout_ticks == 0 && self.rng.gen_bool(0.002) {
+                self.dropout_ticks = self.rng.gen_range(5..30);
+                tracing::debug!("Simulated dropout for {} ticks", self.dropout_ticks);
+            }
+
+            if self.co2_fault_ticks == 0 && self.rng.gen_bool(0.001) {
+                self.co2_fault_ticks = self.rng.gen_range(10..100);
+                tracing::debug!(
+                    "Simulated CO2 sensor fault for {} ticks",
+                    self.co2_fault_ticks
+                );
+            }
+        }
+
+        if self.dropout_ticks > 0 {
+            self.dropout_ticks -= 1;
+            return None;
+        }
+
+        let co2 = if self.co2_fault_ticks > 0 {
+            self.co2_fault_ticks -= 1;
+            None
+        } else {
+            Some((self.co2 + self.rng.gen_range(-5.0..5.0)) as i32)
+        };
+
+        let eco2 = (self.co2 + self.rng.gen_range(-60.0..60.0)).max(400.0);
+        let etvoc = ((eco2 - 400.0) / 5.0 + self.rng.gen_range(0.0..10.0)).max(0.0);
+
+        let sun = 900.0 * daylight(hour) * self.rng.gen_range(0.7..1.0);
+        let lamp = if occupants > 0.0 && sun < 200.0 {
+            300.0
+        } else {
+            0.0
+        };
+
+        let mut error_flags = SensorFaults::empty();
+        error_flags.set(SensorFaults::MHZ19, co2.is_none());
+
+        Some(ClimateData {
+            co2,
+            temperature: (self.temperature + self.rng.gen_range(-0.05..0.05)) as f32,
+            eco2: eco2 as i16,
+            etvoc: etvoc as i16,
+            pressure: self.pressure as f32,
+            humidity: (self.humidity + self.rng.gen_range(-0.3..0.3)) as f32,
+            light: Some((sun + lamp) as f32),
+            error_flags,
+            timestamp: Timestamp::default(),
+        })
+    }
+}
+
+#[async_trait]
+impl ClimateSource for Simulator {
+    fn describe(&self) -> String {
+        match self.options.seed {
+            Some(seed) => format!("simulator (seed {seed})"),
+            None => "simulator".to_string(),
+        }
+    }
+
+    async fn subscribe(
+        &self,
+        on_data: &mut (dyn FnMut(ClimateData) + Send),
+        link: &LinkStats,
+    ) -> Result<()> {
+        let mut ticker = interval(TICK);
+
+        loop {
+            ticker.tick().await;
+
+            let (data, rssi) = {
+                let mut state = self.state.lock().unwrap();
+                (state.next(&self.options), state.rssi as i16)
+            };
+
+            link.record_rssi(rssi);
+            if let Some(data) = data {
+                link.record_notification();
+                on_data(data);
+            }
+        }
+    }
+
+    async fn watch_battery(&self, on_level: &mut (dyn FnMut(u8) + Send)) -> Result<()> {
+        // start almost empty with faults to see the low battery alerts right away
+        let mut level: u8 = if self.options.faults { 25 } else { 100 };
+        let mut ticker = interval(BATTERY_DRAIN_INTERVAL);
+
+        loop {
+            ticker.tick().await;
+            on_level(level);
+            level = level.saturating_sub(1);
+        }
+    }
+
+    async fn read_calibration(&self) -> Result<Option<CalibrationState>> {
+        // a fresh unit, so the calibration hints show up without hardware
+        let uncalibrated = Some(Adjustment {
+            is_calibrated: false,
+            adjust: None,
+        });
+
+        Ok(Some(CalibrationState {
+            co2: Some(false),
+            temperature: uncalibrated,
+            humidity: uncalibrated,
+        }))
+    }
+
+    async fn run_actions(&self, mut actions: mpsc::Receiver<BleAction>) -> Result<()> {
+        while let Some(action) = actions.recv().await {
+            match action {
+                BleAction::CalibrateCo2 { reply } => {
+                    tracing::info!("Simulated CO2 calibration");
+                    let _ = reply.send(Ok(()));
+                }
+                BleAction::CalibrateTemperature { adjust, reply } => {
+                    tracing::info!("Simulated temperature calibration by {adjust}");
+                    let _ = reply.send(Ok(()));
+                }
+                BleAction::CalibrateHumidity { adjust, reply } => {
+                    tracing::info!("Simulated humidity calibration by {adjust}");
+                    let _ = reply.send(Ok(()));
+                }
+                BleAction::Stop => {
+                    tracing::info!("Stopping simulator");
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn disconnect(&self) {}
+}
@@ -40,6 +40,11 @@ const BUTTONS: [Button; 4] = [
 
 pub fn handle_dashboard_key_event(keycode: KeyCode) -> Option<Action> {
     match keycode {
+        KeyCode::Tab => Some(Action::NextDevice),
+        KeyCode::BackTab => Some(Action::PreviousDevice),
+        KeyCode::Char(char @ '1'..='9') => char
+            .to_digit(10)
+            .map(|digit| Action::SelectDevice(digit as usize - 1)),
         KeyCode::Char(char) => BUTTONS.iter().find_map(|button| {
             if button.char == char {
                 Some(button.control)
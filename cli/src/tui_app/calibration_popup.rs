@@ -1,4 +1,10 @@
 use super::Action;
+use crate::{
+    calibration::{Co2Readiness, Quantity, CO2_PLATEAU_WINDOW},
+    calibration_log::{self, CalibrationEntry, Metric},
+    climate_data::Timestamp,
+    history::History,
+};
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
     layout::{Alignment, Constraint, Layout, Rect},
@@ -16,6 +22,11 @@ enum Tab {
     Co2,
     #[strum(to_string = "Temperature Calibration")]
     Temperature { input: String },
+    #[strum(to_string = "Humidity Calibration")]
+    Humidity { input: String },
+    /// Calibrations of the device from the newest
+    #[strum(to_string = "Log")]
+    Log { entries: Vec<CalibrationEntry> },
 }
 
 impl Tab {
@@ -23,6 +34,17 @@ impl Tab {
         match self {
             Tab::Co2 => 'c',
             Tab::Temperature { .. } => 't',
+            Tab::Humidity { .. } => 'h',
+            Tab::Log { .. } => 'l',
+        }
+    }
+
+    /// The tabs calibrating against a reading typed in by the user
+    fn reference_input(&mut self) -> Option<(Quantity, &mut String)> {
+        match self {
+            Tab::Co2 | Tab::Log { .. } => None,
+            Tab::Temperature { input } => Some((Quantity::Temperature, input)),
+            Tab::Humidity { input } => Some((Quantity::Humidity, input)),
         }
     }
 
@@ -40,9 +62,25 @@ impl Tab {
     }
 }
 
+/// Where the calibration started from the popup is at
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalibrationProgress {
+    Running,
+    Done(String),
+    Failed(String),
+}
+
+/// Longest input that still makes sense for a reference reading, e.g. `-12.25`
+const MAX_INPUT_LENGTH: usize = 6;
+
 #[derive(Debug, Default)]
 pub struct CalibrationPopup {
     tab: Tab,
+    /// Why the input can not be used or the log can not be read
+    input_error: Option<String>,
+    progress: Option<CalibrationProgress>,
+    /// When the user put the sensor into fresh air for the CO2 calibration
+    co2_wizard: Option<Timestamp>,
 }
 
 /// helper function to create a centered rect using up certain percentage of the available rect `r`
@@ -64,41 +102,243 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
 
 impl CalibrationPopup {
     pub fn co2() -> Self {
-        Self { tab: Tab::Co2 }
+        Self {
+            tab: Tab::Co2,
+            ..Default::default()
+        }
     }
     pub fn temperature() -> Self {
         Self {
             tab: Tab::Temperature {
                 input: "".to_string(),
             },
+            ..Default::default()
         }
     }
 
-    fn render_co2_tab(&self, area: Rect, f: &mut Frame) {
-        let text = Paragraph::new(vec![
+    pub fn humidity() -> Self {
+        Self {
+            tab: Tab::Humidity {
+                input: "".to_string(),
+            },
+            ..Default::default()
+        }
+    }
+
+    pub fn log(device: &str) -> Self {
+        let (entries, input_error) = match calibration_log::read_device(device) {
+            Ok(entries) => (entries, None),
+            Err(e) => (
+                Vec::new(),
+                Some(format!("Failed to read the calibration log: {e}")),
+            ),
+        };
+
+        Self {
+            tab: Tab::Log { entries },
+            input_error,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_progress(&mut self, progress: CalibrationProgress) {
+        self.progress = Some(progress);
+    }
+
+    /// The reference is typed in the configured unit, the device is calibrated in its own
+    fn parse_reference(quantity: Quantity, input: &str) -> Result<f32, String> {
+        let reference = input
+            .replace(',', ".")
+            .parse::<f32>()
+            .map_err(|_| format!("Enter the {} as a number, e.g. 22.5", quantity.name()))?;
+
+        quantity.reference_from_display(reference)
+    }
+
+    /// `None` until the CO2 wizard is started
+    pub fn co2_readiness(&self, history: &History) -> Option<Co2Readiness> {
+        self.co2_wizard
+            .map(|started_at| Co2Readiness::of(history, started_at))
+    }
+
+    fn render_co2_tab(&self, readiness: Option<Co2Readiness>, area: Rect, f: &mut Frame) {
+        let mut lines = vec![
           Line::from(""),
-          Line::from("Calibration of CO2 is performed by a controlled measurement which will be used as a 400ppm point. For better results put the sensor outside or in a well ventilated room."),
-        ]).wrap(Wrap { trim: true });
+          Line::from("Calibration of CO2 takes the current level as the 400ppm point, so it has to be done in fresh air. Put the sensor outside or next to a wide open window and press [Enter], the calibration unlocks once the readings settled."),
+          Line::from(""),
+        ];
 
-        f.render_widget(text, area);
+        if let Some(readiness) = readiness {
+            let remaining = readiness.remaining.as_secs();
+            lines.push(Line::from(if remaining > 0 {
+                format!(
+                    "Keep the sensor in fresh air, calibration unlocks in {:02}:{:02}",
+                    remaining / 60,
+                    remaining % 60
+                )
+            } else {
+                "The sensor had enough time to settle".to_string()
+            }));
+
+            lines.push(match readiness.plateau {
+                Some(plateau) => Line::from(vec![
+                    Span::from(format!(
+                        "CO2 over the last {} minutes: {:.0} ppm ±{:.0} ppm ",
+                        CO2_PLATEAU_WINDOW.as_secs() / 60,
+                        plateau.mean,
+                        plateau.std_dev
+                    )),
+                    if plateau.is_stable() {
+                        Span::styled("stable", Style::default().fg(Color::Green))
+                    } else {
+                        Span::styled("still changing", Style::default().fg(Color::Yellow))
+                    },
+                ]),
+                None => Line::from("Waiting for more readings…"),
+            });
+
+            if let Some(warning) = readiness
+                .plateau
+                .and_then(|plateau| plateau.outdoor_warning())
+            {
+                lines.push(Line::styled(warning, Style::default().fg(Color::Yellow)));
+            }
+        }
+
+        f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: true }), area);
     }
 
-    fn render_temperature_tab(&self, area: Rect, f: &mut Frame) {
+    fn render_reference_tab(
+        &self,
+        quantity: Quantity,
+        input: &str,
+        measured: Option<f32>,
+        area: Rect,
+        f: &mut Frame,
+    ) {
+        let (instrument, step) = match quantity {
+            Quantity::Temperature => ("thermometer", "whole °C"),
+            Quantity::Humidity => ("hygrometer", "whole percents"),
+        };
+        let measured = match measured {
+            Some(measured) => format!("{measured:.1}{}", quantity.display_unit()),
+            None => "no valid reading yet".to_string(),
+        };
+
         let text = Paragraph::new(vec![
             Line::from(""),
-            Line::from(
-                "To calibrate temperature please enter the desired temperature in Celsius. It will be used as an ideal temperature point and all the values will be scaled accordingly.",
-            ),
-        ]).wrap(Wrap { trim: true });
+            Line::from(format!(
+                "To calibrate {} please enter the value shown by a reference {instrument} next to the sensor. The device will adjust all its readings by the difference, rounded to {step}.",
+                quantity.name()
+            )),
+            Line::from(""),
+            Line::from(format!("The device reads: {measured}")),
+            Line::from(vec![
+                Span::from(format!("Reference {}: ", quantity.name())),
+                Span::styled(
+                    format!("{input}_"),
+                    Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+                ),
+                Span::from(format!(" {}", quantity.display_unit())),
+            ]),
+        ])
+        .wrap(Wrap { trim: true });
 
         f.render_widget(text, area);
     }
 
-    fn render_selected_tab(&self, area: Rect, f: &mut Frame) {
-        match self.tab {
-            Tab::Co2 => self.render_co2_tab(area, f),
-            Tab::Temperature { .. } => self.render_temperature_tab(area, f),
+    fn render_log_tab(&self, entries: &[CalibrationEntry], area: Rect, f: &mut Frame) {
+        let separator = Span::styled(" │ ", Style::default().fg(Color::Gray));
+        // when each sensor was last calibrated successfully, tells which one is due
+        let mut last_calibrated = vec![Span::from("Last calibrated: ")];
+        for (index, metric) in [Metric::Co2, Metric::Temperature, Metric::Humidity]
+            .into_iter()
+            .enumerate()
+        {
+            if index > 0 {
+                last_calibrated.push(separator.clone());
+            }
+            let age = entries
+                .iter()
+                .find(|entry| entry.metric == metric && entry.is_accepted())
+                .map(CalibrationEntry::age);
+            last_calibrated.push(Span::from(format!(
+                "{metric} {}",
+                age.as_deref().unwrap_or("never")
+            )));
         }
+
+        let mut lines = vec![Line::from(""), Line::from(last_calibrated), Line::from("")];
+        if entries.is_empty() {
+            lines.push(Line::from("Nothing was calibrated from this machine yet"));
+        }
+        lines.extend(entries.iter().map(|entry| {
+            let outcome_style = if entry.is_accepted() {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default().fg(Color::Red)
+            };
+
+            Line::from(vec![
+                Span::from(format!(
+                    "{}  {}: {} ",
+                    entry.timestamp.format("%Y-%m-%d %H:%M").unwrap_or_default(),
+                    entry.metric,
+                    entry.describe()
+                )),
+                Span::styled(entry.outcome(), outcome_style),
+            ])
+        }));
+
+        f.render_widget(Paragraph::new(lines), area);
+    }
+
+    fn render_status(&self, area: Rect, f: &mut Frame) {
+        let status = match (&self.input_error, &self.progress) {
+            (Some(error), _) => Span::styled(error.clone(), Style::default().fg(Color::Red)),
+            (None, Some(CalibrationProgress::Running)) => {
+                Span::styled("Calibrating...", Style::default().fg(Color::Yellow))
+            }
+            (None, Some(CalibrationProgress::Done(message))) => {
+                Span::styled(message.clone(), Style::default().fg(Color::Green))
+            }
+            (None, Some(CalibrationProgress::Failed(message))) => {
+                Span::styled(message.clone(), Style::default().fg(Color::Red))
+            }
+            (None, None) => return,
+        };
+
+        f.render_widget(
+            Paragraph::new(Line::from(status))
+                .style(Style::default().add_modifier(Modifier::BOLD))
+                .wrap(Wrap { trim: true }),
+            area,
+        );
+    }
+
+    fn render_selected_tab(
+        &self,
+        history: &History,
+        readiness: Option<Co2Readiness>,
+        area: Rect,
+        f: &mut Frame,
+    ) {
+        let [tab_area, status_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(2)]).areas(area);
+
+        match &self.tab {
+            Tab::Co2 => self.render_co2_tab(readiness, tab_area, f),
+            Tab::Temperature { input } => {
+                let measured = Quantity::Temperature.displayed_reading(history);
+                self.render_reference_tab(Quantity::Temperature, input, measured, tab_area, f)
+            }
+            Tab::Humidity { input } => {
+                let measured = Quantity::Humidity.displayed_reading(history);
+                self.render_reference_tab(Quantity::Humidity, input, measured, tab_area, f)
+            }
+            Tab::Log { entries } => self.render_log_tab(entries, tab_area, f),
+        }
+        self.render_status(status_area, f);
     }
 
     fn render_tabs(&self, area: Rect, f: &mut Frame) {
@@ -106,6 +346,8 @@ impl CalibrationPopup {
         let selected_tab_index = match self.tab {
             Tab::Co2 => 0,
             Tab::Temperature { .. } => 1,
+            Tab::Humidity { .. } => 2,
+            Tab::Log { .. } => 3,
         };
 
         let tabs = ratatui::widgets::Tabs::new(titles)
@@ -121,17 +363,25 @@ impl CalibrationPopup {
         f.render_widget(tabs, area)
     }
 
-    fn render_control(&self, area: Rect, f: &mut Frame) {
+    fn render_control(&self, readiness: Option<Co2Readiness>, area: Rect, f: &mut Frame) {
         let area = centered_rect(40, 100, area);
-        let text = Paragraph::new(vec![Line::from(" Press [Enter] to calibrate ")])
+        let (text, color) = match (&self.tab, readiness) {
+            (Tab::Log { .. }, _) => (" Press [Esc] to close ", Color::Gray),
+            (Tab::Co2, None) => (" Press [Enter] to start ", Color::Blue),
+            (Tab::Co2, Some(readiness)) if !readiness.is_ready() => {
+                (" Waiting for the sensor to settle ", Color::Gray)
+            }
+            (Tab::Co2, Some(readiness)) if readiness.needs_confirmation() => {
+                (" Press [y] to calibrate anyway ", Color::Yellow)
+            }
+            _ => (" Press [Enter] to calibrate ", Color::Blue),
+        };
+
+        let text = Paragraph::new(vec![Line::from(text)])
             .alignment(Alignment::Center)
             .block(
                 Block::default()
-                    .style(
-                        Style::default()
-                            .fg(Color::Blue)
-                            .add_modifier(Modifier::BOLD),
-                    )
+                    .style(Style::default().fg(color).add_modifier(Modifier::BOLD))
                     .borders(Borders::ALL)
                     .border_type(ratatui::widgets::BorderType::Rounded),
             )
@@ -140,14 +390,16 @@ impl CalibrationPopup {
         f.render_widget(text, area);
     }
 
-    pub fn render(&self, f: &mut Frame) {
+    pub fn render(&self, history: &History, f: &mut Frame) {
+        let readiness = self.co2_readiness(history);
+
         let popup_block = Block::default()
             .title("Enter a new key-value pair")
             .borders(Borders::LEFT | Borders::RIGHT)
             .border_style(Style::default().fg(Color::Black))
             .style(Style::default().bg(Color::DarkGray));
 
-        let area = centered_rect(60, 25, f.area());
+        let area = centered_rect(60, 40, f.area());
         f.render_widget(Clear, area);
         f.render_widget(popup_block, area);
 
@@ -158,7 +410,7 @@ impl CalibrationPopup {
         ])
         .areas(area);
         self.render_tabs(tabs_area, f);
-        self.render_control(control_area, f);
+        self.render_control(readiness, control_area, f);
 
         let [_, body_area, _] = Layout::horizontal([
             Constraint::Length(1),
@@ -166,19 +418,64 @@ impl CalibrationPopup {
             Constraint::Length(1),
         ])
         .areas(body_area);
-        self.render_selected_tab(body_area, f);
+        self.render_selected_tab(history, readiness, body_area, f);
     }
 
-    pub fn handle_key(&self, key: KeyEvent) -> Option<Action> {
+    /// `readiness` is the one of [`Self::co2_readiness`], taken before the popup is locked
+    /// for the key
+    pub fn handle_key(&mut self, key: KeyEvent, readiness: Option<Co2Readiness>) -> Option<Action> {
+        // switching to the open tab would reset it, including a running CO2 wizard
+        let is_open = |char| self.tab.get_char() == char;
         match key.code {
-            KeyCode::Char('t') => Some(Action::OpenCalibrateTemperaturePopup),
-            KeyCode::Char('c') => Some(Action::OpenCalibrateCo2Popup),
-            KeyCode::Esc => Some(Action::OpenDashboard),
-            KeyCode::Enter => match self.tab {
-                Tab::Co2 => Some(Action::CalibrateCo2),
-                Tab::Temperature { .. } => todo!(),
+            KeyCode::Char('t') if !is_open('t') => {
+                return Some(Action::OpenCalibrateTemperaturePopup)
+            }
+            KeyCode::Char('h') if !is_open('h') => return Some(Action::OpenCalibrateHumidityPopup),
+            KeyCode::Char('c') if !is_open('c') => return Some(Action::OpenCalibrateCo2Popup),
+            KeyCode::Char('l') if !is_open('l') => return Some(Action::OpenCalibrationLogPopup),
+            KeyCode::Esc => return Some(Action::OpenDashboard),
+            // one calibration at a time, the device answers within a few seconds
+            KeyCode::Enter if self.progress == Some(CalibrationProgress::Running) => return None,
+            KeyCode::Enter if self.tab == Tab::Co2 => {
+                return match readiness {
+                    None => {
+                        self.co2_wizard = Some(Timestamp::default());
+                        None
+                    }
+                    Some(readiness) if readiness.is_ready() && !readiness.needs_confirmation() => {
+                        Some(Action::CalibrateCo2)
+                    }
+                    Some(_) => None,
+                };
+            }
+            KeyCode::Char('y')
+                if self.tab == Tab::Co2 && readiness.is_some_and(|r| r.needs_confirmation()) =>
+            {
+                return Some(Action::CalibrateCo2)
+            }
+            _ => {}
+        }
+
+        let (quantity, input) = self.tab.reference_input()?;
+        match key.code {
+            KeyCode::Enter => match Self::parse_reference(quantity, input) {
+                Ok(reference) => return Some(Action::Calibrate(quantity, reference)),
+                Err(error) => self.input_error = Some(error),
             },
-            _ => None,
+            KeyCode::Char(char)
+                if (char.is_ascii_digit() || matches!(char, '.' | ',' | '-'))
+                    && input.len() < MAX_INPUT_LENGTH =>
+            {
+                input.push(char);
+                self.input_error = None;
+            }
+            KeyCode::Backspace => {
+                input.pop();
+                self.input_error = None;
+            }
+            _ => {}
         }
+
+        None
     }
 }
@@ -0,0 +1,77 @@
+use crate::calibration::{Adjustment, CalibrationState, Quantity};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+
+fn adjustment_span(quantity: Quantity, adjustment: &Adjustment) -> Span<'static> {
+    match adjustment {
+        Adjustment {
+            is_calibrated: false,
+            ..
+        } => Span::styled(
+            format!("{} not calibrated", quantity.name()),
+            Style::default().fg(Color::Yellow),
+        ),
+        Adjustment {
+            adjust: Some(adjust),
+            ..
+        } => Span::from(format!(
+            "{} adjusted by {adjust:+}{}",
+            quantity.name(),
+            quantity.unit()
+        )),
+        Adjustment { adjust: None, .. } => Span::from(format!("{} calibrated", quantity.name())),
+    }
+}
+
+/// What the device reported about its calibration, a fresh unit should be calibrated first
+pub fn render_calibration_status(state: &CalibrationState, area: Rect, f: &mut Frame) {
+    let mut parts = Vec::new();
+    if let Some(is_calibrated) = state.co2 {
+        parts.push(if is_calibrated {
+            Span::from("CO2 calibrated")
+        } else {
+            Span::styled("CO2 not calibrated", Style::default().fg(Color::Yellow))
+        });
+    }
+    if let Some(adjustment) = &state.temperature {
+        parts.push(adjustment_span(Quantity::Temperature, adjustment));
+    }
+    if let Some(adjustment) = &state.humidity {
+        parts.push(adjustment_span(Quantity::Humidity, adjustment));
+    }
+
+    if parts.is_empty() {
+        return;
+    }
+
+    let needs_calibration = state.co2 == Some(false)
+        || [state.temperature, state.humidity]
+            .iter()
+            .flatten()
+            .any(|adjustment| !adjustment.is_calibrated);
+
+    let separator = Span::styled(" │ ", Style::default().fg(Color::DarkGray));
+    let mut spans = vec![Span::from(" Calibration: ")];
+    for (index, part) in parts.into_iter().enumerate() {
+        if index > 0 {
+            spans.push(separator.clone());
+        }
+        spans.push(part);
+    }
+    if needs_calibration {
+        spans.push(Span::styled(
+            " (press [c] to calibrate)",
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+
+    f.render_widget(
+        Paragraph::new(Line::from(spans)).style(Style::default().fg(Color::Gray)),
+        area,
+    )
+}
@@ -0,0 +1,39 @@
+use crate::{connection::ConnectionState, devices::Device};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::Paragraph,
+    Frame,
+};
+use std::time::Instant;
+
+/// Message about the connection of the device, nothing while the measurements flow
+pub fn connection_banner(device: &Device) -> Option<String> {
+    match device.state() {
+        ConnectionState::Streaming => None,
+        ConnectionState::Scanning => Some("Looking for the sensor…".to_string()),
+        ConnectionState::Connecting => Some("Connecting to the sensor…".to_string()),
+        ConnectionState::Discovering => Some("Connected, waiting for measurements…".to_string()),
+        ConnectionState::Degraded => Some(format!(
+            "No measurements for {}s, waiting for the sensor…",
+            device.silence().unwrap_or_default().as_secs()
+        )),
+        ConnectionState::BackingOff { attempt, retry_at } => Some(format!(
+            "{}, reconnecting in {}s (attempt {attempt})…",
+            device.last_error().unwrap_or("Connection lost".to_string()),
+            retry_at.saturating_duration_since(Instant::now()).as_secs()
+        )),
+        ConnectionState::Finished => Some("No more measurements, the recording ended".to_string()),
+    }
+}
+
+pub fn render_connection_banner(text: String, area: Rect, f: &mut Frame) {
+    let banner = Paragraph::new(format!(" {text}")).style(
+        Style::default()
+            .fg(Color::Black)
+            .bg(Color::Yellow)
+            .add_modifier(Modifier::BOLD),
+    );
+
+    f.render_widget(banner, area)
+}
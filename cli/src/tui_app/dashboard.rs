@@ -3,7 +3,11 @@ use super::{
     chart::{render_chart, ChartOptions},
     dumb_advice::render_dumb_advice_block,
 };
-use crate::{climate_data::ClimateData, history::History};
+use crate::{
+    climate_data::ClimateData,
+    config,
+    history::{FaultHistory, History},
+};
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
@@ -15,21 +19,71 @@ use ratatui::{
 
 pub struct DashboardView {}
 
+/// Chart points converted from the unit the history keeps them in
+fn converted(points: &[(f64, f64)], convert: impl Fn(f32) -> f32) -> Vec<(f64, f64)> {
+    points
+        .iter()
+        .map(|(ts, value)| (*ts, convert(*value as f32) as f64))
+        .collect()
+}
+
+/// Readings of a faulty sensor stay visible, but flagged so nobody trusts a stuck value
+fn value_style(color: Color, is_valid: bool) -> Style {
+    Style::default()
+        .fg(if is_valid { color } else { Color::DarkGray })
+        .add_modifier(if is_valid {
+            Modifier::BOLD
+        } else {
+            Modifier::CROSSED_OUT
+        })
+}
+
+fn overview_title(faults: &FaultHistory) -> Span<'static> {
+    let current = faults.current();
+    if current.is_empty() {
+        return Span::styled(
+            " Climate Right Now ",
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        );
+    }
+
+    let failing = current
+        .iter_names()
+        .map(|(name, sensor)| match faults.failing_since(sensor) {
+            Some(since) => format!("{name} since {}", since.format("%H:%M").unwrap_or_default()),
+            None => name.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    Span::styled(
+        format!(" ⚠ Sensor fault: {failing} "),
+        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+    )
+}
+
 impl DashboardView {
-    fn render_overview(last_climate_data: &ClimateData, f: &mut Frame, area: Rect) {
+    fn render_overview(
+        last_climate_data: &ClimateData,
+        faults: &FaultHistory,
+        f: &mut Frame,
+        area: Rect,
+    ) {
+        let thresholds = config::thresholds();
+        let (temperature, pressure) = (config::units().temperature, config::units().pressure());
         let text = vec![
             Line::from(""),
             Line::from(vec![
                 Span::from(" CO2: "),
                 Span::styled(
                     format!("{} ppm ", last_climate_data.co2.unwrap_or(400)),
-                    Style::default()
-                        .fg(Color::Cyan)
-                        .add_modifier(Modifier::BOLD),
+                    value_style(Color::Cyan, last_climate_data.is_co2_valid()),
                 ),
                 Span::from(match last_climate_data.co2 {
-                    Some(co2) if co2 > 1000 => "🥵",
-                    Some(co2) if co2 > 800 => "😨",
+                    Some(co2) if co2 > thresholds.co2_alert => "🥵",
+                    Some(co2) if co2 > thresholds.co2_warning => "😨",
                     Some(co2) if co2 > 600 => "😗",
                     Some(co2) if co2 > 400 => "😊",
                     Some(_) => "😌",
@@ -40,29 +94,27 @@ impl DashboardView {
                 Span::from(" eCO2: "),
                 Span::styled(
                     format!("{} ppm", last_climate_data.eco2),
-                    Style::default()
-                        .fg(Color::Gray)
-                        .add_modifier(Modifier::BOLD),
+                    value_style(Color::Gray, last_climate_data.is_eco2_valid()),
                 ),
             ]),
             Line::from(vec![
                 Span::from(" TVOC: "),
                 Span::styled(
                     format!("{:.0} ppb", last_climate_data.etvoc),
-                    Style::default()
-                        .fg(Color::Gray)
-                        .add_modifier(Modifier::BOLD),
+                    value_style(Color::Gray, last_climate_data.is_eco2_valid()),
                 ),
             ]),
             Line::from(vec![
                 Span::from(" Temperature: "),
                 Span::styled(
                     format!(
-                        "{:.1}°C ({:.2}°F)",
-                        last_climate_data.temperature,
-                        last_climate_data.temperature * 9.0 / 5.0 + 32.0
+                        "{:.1}{} ({:.2}{})",
+                        temperature.convert(last_climate_data.temperature),
+                        temperature.symbol(),
+                        temperature.other().convert(last_climate_data.temperature),
+                        temperature.other().symbol(),
                     ),
-                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    value_style(Color::Red, last_climate_data.is_temperature_valid()),
                 ),
             ]),
             Line::from(vec![
@@ -78,22 +130,20 @@ impl DashboardView {
                 Span::from(" Pressure: "),
                 Span::styled(
                     format!(
-                        "{:.2}mm Hg ({:.2} hPa)",
-                        last_climate_data.pressure * 0.750_063_8,
-                        last_climate_data.pressure,
+                        "{:.2} {} ({:.2} {})",
+                        pressure.convert(last_climate_data.pressure),
+                        pressure.symbol(),
+                        pressure.other().convert(last_climate_data.pressure),
+                        pressure.other().symbol(),
                     ),
-                    Style::default()
-                        .fg(Color::Magenta)
-                        .add_modifier(Modifier::BOLD),
+                    value_style(Color::Magenta, last_climate_data.is_pressure_valid()),
                 ),
             ]),
             Line::from(vec![
                 Span::from(" Light: "),
                 Span::styled(
                     format!("{:.0} lux ", last_climate_data.light.unwrap_or(0.0)),
-                    Style::default()
-                        .fg(Color::Yellow)
-                        .add_modifier(Modifier::BOLD),
+                    value_style(Color::Yellow, last_climate_data.is_light_valid()),
                 ),
                 Span::from(match last_climate_data.light {
                     Some(light) if light > 400.0 => "🌞",
@@ -106,18 +156,11 @@ impl DashboardView {
         let block = Block::default()
             .borders(Borders::ALL)
             .title_alignment(Alignment::Center)
-            .title(Span::styled(
-                " Climate Right Now ",
-                Style::default()
-                    .fg(Color::White)
-                    .add_modifier(Modifier::BOLD),
-            ));
+            .title(overview_title(faults));
         let paragraph = Paragraph::new(text).block(block).wrap(Wrap { trim: true });
         f.render_widget(paragraph, area);
     }
-    pub fn render_dashboard(history: &History, f: &mut Frame) {
-        let size = f.area();
-
+    pub fn render_dashboard(history: &History, f: &mut Frame, size: Rect) {
         let latest_climate_data = if let Some(latest_climate_data) = history.latest_climate_data {
             latest_climate_data
         } else {
@@ -148,7 +191,7 @@ impl DashboardView {
             })
             .split(main_layout[0]);
 
-        Self::render_overview(&latest_climate_data, f, top_layout[1]);
+        Self::render_overview(&latest_climate_data, &history.faults, f, top_layout[1]);
         if let Some(advice_layout) = top_layout.first() {
             render_dumb_advice_block(&latest_climate_data, advice_layout, f)
         }
@@ -162,7 +205,9 @@ impl DashboardView {
                 f,
                 ChartOptions {
                     unit_of_measurement: "ppm",
-                    current_measure: latest_climate_data.co2,
+                    current_measure: latest_climate_data
+                        .co2
+                        .filter(|_| latest_climate_data.is_co2_valid()),
                     label: "CO2",
                     color: Color::Cyan,
                     bounds: [400.0, 2000.],
@@ -185,6 +230,8 @@ impl DashboardView {
         }
 
         if let Some(horizontal_layout) = main_layout.get(2) {
+            let units = config::units();
+            let (temperature, pressure) = (units.temperature, units.chart_pressure());
             let horizontal_charts_layout = Layout::default()
                 .direction(if horizontal_layout.width > 80 {
                     Direction::Horizontal
@@ -198,22 +245,31 @@ impl DashboardView {
             render_chart(
                 f,
                 ChartOptions {
-                    unit_of_measurement: "°C",
+                    unit_of_measurement: temperature.symbol(),
                     label: "Temperature",
-                    current_measure: Some(latest_climate_data.temperature),
+                    current_measure: Some(temperature.convert(latest_climate_data.temperature))
+                        .filter(|_| latest_climate_data.is_temperature_valid()),
                     color: Color::LightRed,
                     window: history.temperature_history.get_window(|(ts, _)| *ts),
-                    bounds: history
-                        .temperature_minmax
-                        .as_ref()
-                        .map(|r| [r.start.floor(), r.end.ceil()])
-                        .unwrap_or([0.0, 40.0]),
+                    bounds: {
+                        let [start, end] = history
+                            .temperature_minmax
+                            .as_ref()
+                            .map(|r| [r.start, r.end])
+                            .unwrap_or([0.0, 40.0])
+                            .map(|celsius| temperature.convert(celsius as f32) as f64);
+
+                        [start.floor(), end.ceil()]
+                    },
                     area: horizontal_charts_layout[0],
                     datasets: vec![Dataset::default()
-                        .name("°C")
+                        .name(temperature.symbol())
                         .marker(symbols::Marker::Braille)
                         .style(Style::default().fg(Color::LightRed))
-                        .data(history.temperature_history.as_ratatui_dataset())],
+                        .data(&converted(
+                            history.temperature_history.as_ratatui_dataset(),
+                            |celsius| temperature.convert(celsius),
+                        ))],
                 },
             );
 
@@ -221,8 +277,9 @@ impl DashboardView {
                 render_chart(
                     f,
                     ChartOptions {
-                        unit_of_measurement: "hPa",
-                        current_measure: Some(latest_climate_data.pressure),
+                        unit_of_measurement: pressure.symbol(),
+                        current_measure: Some(pressure.convert(latest_climate_data.pressure))
+                            .filter(|_| latest_climate_data.is_pressure_valid()),
                         label: "Atmospheric Pressure",
                         color: Color::Blue,
                         window: history.pressure_history.get_window(|(ts, _)| *ts),
@@ -234,6 +291,7 @@ impl DashboardView {
                                 .unwrap_or(950.0..1020.0);
 
                             [range.start.min(1000.0), range.end.max(1020.0)]
+                                .map(|hpa| pressure.convert(hpa as f32) as f64)
                         },
                         area: *pressure_layout,
                         datasets: vec![
@@ -246,18 +304,21 @@ impl DashboardView {
                                         .pressure_history
                                         .as_ratatui_dataset()
                                         .iter()
-                                        .map(|(ts, _)| (*ts, 1013.25f64))
+                                        .map(|(ts, _)| (*ts, pressure.convert(1013.25) as f64))
                                         .collect::<Vec<_>>(),
                                 ),
                             Dataset::default()
-                                .name("hectoPascals")
+                                .name(pressure.symbol())
                                 .marker(symbols::Marker::HalfBlock)
                                 .style(Style::default().fg(Color::Blue))
-                                .data(history.pressure_history.as_ratatui_dataset()),
+                                .data(&converted(
+                                    history.pressure_history.as_ratatui_dataset(),
+                                    |hpa| pressure.convert(hpa),
+                                )),
                         ],
                     },
                 );
             }
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,41 @@
+use crate::{connection::ConnectionState, devices::Device};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Tabs,
+    Frame,
+};
+use std::sync::Arc;
+
+pub fn render_device_tabs(devices: &[Arc<Device>], selected: usize, area: Rect, f: &mut Frame) {
+    let titles = devices.iter().enumerate().map(|(i, device)| {
+        let status_style = match device.state() {
+            ConnectionState::Streaming => Style::default().fg(Color::Green),
+            ConnectionState::BackingOff { .. } => Style::default().fg(Color::Red),
+            _ => Style::default().fg(Color::Yellow),
+        };
+
+        Line::from(vec![
+            Span::from(" "),
+            Span::styled(format!("[{}]", i + 1), Style::default().fg(Color::Blue)),
+            Span::from(" "),
+            Span::styled("●", status_style),
+            Span::from(" "),
+            Span::styled(device.label.clone(), Style::default().fg(Color::White)),
+            Span::from(" "),
+        ])
+    });
+
+    let tabs = Tabs::new(titles)
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )
+        .select(selected)
+        .padding("", "")
+        .divider(" ");
+
+    f.render_widget(tabs, area)
+}
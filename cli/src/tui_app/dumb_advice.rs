@@ -5,32 +5,33 @@ use ratatui::{
     Frame,
 };
 
-use crate::climate_data::ClimateData;
+use crate::{climate_data::ClimateData, config};
 
 fn create_dumb_climate_advice(climate_data: &ClimateData) -> String {
     let mut advice = String::from("\n");
+    let thresholds = config::thresholds();
 
     // CO2 levels
     if let Some(co2) = climate_data.co2 {
-        if co2 > 1000 {
+        if co2 > thresholds.co2_alert {
             advice
                 .push_str("With CO2 this high, are we hosting a dinosaur summit? Open a window!\n");
-        } else if co2 < 800 {
+        } else if co2 < thresholds.co2_warning {
             advice.push_str("CO2 levels like a sci-fi space drama—all clear for now!\n");
         }
     }
 
     // Temperature extremes
-    if climate_data.temperature > 25.0 {
+    if climate_data.temperature > thresholds.temperature_max {
         advice.push_str("It's hotter than a tech startup in here. Crack a window!\n");
-    } else if climate_data.temperature < 20.0 {
+    } else if climate_data.temperature < thresholds.temperature_min {
         advice.push_str("It's colder than my ex's heart. Seal those leaks!\n");
     }
 
     // Humidity adventures
-    if climate_data.humidity > 70.0 {
+    if climate_data.humidity > thresholds.humidity_max {
         advice.push_str("Humidity's higher than a hippie at a concert. Time for some fresh air!\n");
-    } else if climate_data.humidity < 30.0 {
+    } else if climate_data.humidity < thresholds.humidity_min {
         advice.push_str("Drier than a British comedy—might wanna close that window.\n");
     }
 
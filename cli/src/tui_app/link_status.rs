@@ -0,0 +1,99 @@
+use crate::bluetooth::LinkQuality;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+use std::time::Duration;
+
+fn rssi_span(rssi: Option<i16>) -> Span<'static> {
+    match rssi {
+        Some(rssi) => Span::styled(
+            format!("{rssi} dBm"),
+            Style::default().fg(match rssi {
+                -67.. => Color::Green,
+                -80..=-68 => Color::Yellow,
+                _ => Color::Red,
+            }),
+        ),
+        None => Span::styled("n/a", Style::default().fg(Color::DarkGray)),
+    }
+}
+
+fn seconds(duration: Option<Duration>) -> String {
+    duration
+        .map(|duration| format!("{:.1}s", duration.as_secs_f32()))
+        .unwrap_or("n/a".to_string())
+}
+
+pub fn battery_span(level: u8) -> Span<'static> {
+    Span::styled(
+        format!("{level}%"),
+        Style::default().fg(match level {
+            50.. => Color::Green,
+            20..=49 => Color::Yellow,
+            _ => Color::Red,
+        }),
+    )
+}
+
+/// One line with the health of the link, tells a dying connection apart from stable air
+pub fn render_link_status(link: &LinkQuality, battery: Option<u8>, area: Rect, f: &mut Frame) {
+    let separator = Span::styled(" │ ", Style::default().fg(Color::DarkGray));
+    let counter_style = |count: u64| {
+        if count > 0 {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default().fg(Color::Gray)
+        }
+    };
+
+    let mut spans = vec![Span::from(" ")];
+    if let Some(level) = battery {
+        spans.extend([
+            Span::from("Battery "),
+            battery_span(level),
+            separator.clone(),
+        ]);
+    }
+
+    spans.extend([
+        Span::from("RSSI "),
+        rssi_span(link.rssi),
+        separator.clone(),
+        Span::from(format!(
+            "every {} (max {})",
+            seconds(link.mean_interval()),
+            seconds(link.max_interval())
+        )),
+        separator.clone(),
+        Span::from(format!("{} received", link.notifications)),
+        separator.clone(),
+        Span::styled(
+            format!("{} decode failures", link.decode_failures),
+            counter_style(link.decode_failures),
+        ),
+        separator.clone(),
+    ]);
+    // only worth the room once messages actually got split and lost
+    if link.dropped_fragments > 0 {
+        spans.extend([
+            Span::styled(
+                format!("{} dropped fragments", link.dropped_fragments),
+                counter_style(link.dropped_fragments),
+            ),
+            separator,
+        ]);
+    }
+    spans.push(Span::styled(
+        format!("{} reconnects", link.reconnects),
+        counter_style(link.reconnects),
+    ));
+
+    f.render_widget(
+        Paragraph::new(Line::from(spans)).style(Style::default().fg(Color::Gray)),
+        area,
+    )
+}
@@ -1,26 +1,47 @@
 mod buttons;
 mod calibration_popup;
+mod calibration_status;
 mod chart;
+mod connection_banner;
 mod dashboard;
+mod device_tabs;
 mod dumb_advice;
+mod link_status;
+mod window_status;
 
 use self::{
-    buttons::handle_dashboard_key_event, calibration_popup::CalibrationPopup,
+    buttons::handle_dashboard_key_event,
+    calibration_popup::{CalibrationPopup, CalibrationProgress},
+    calibration_status::render_calibration_status,
+    connection_banner::{connection_banner, render_connection_banner},
     dashboard::DashboardView,
+    device_tabs::render_device_tabs,
+    link_status::render_link_status,
+    window_status::{render_window_status, WINDOW_STATUS_WIDTH},
+};
+use crate::{
+    ble_actions::BleAction,
+    calibration::{self, Co2Readiness, Quantity},
+    devices::Devices,
+    error::Result,
 };
-use crate::{ble_actions::BleAction, history::History};
 use crossterm::event::{self, Event, KeyCode};
 use ratatui::{
     backend::{Backend, CrosstermBackend},
+    layout::{Constraint, Layout},
     Terminal,
 };
 use std::{
-    error::Error,
     io::Stdout,
-    ops::Deref,
-    sync::{Arc, Mutex, RwLock},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant},
 };
-use tokio::sync::mpsc::Sender;
+
+/// Keeps the connection status fresh while no measurements trigger a redraw
+const IDLE_REDRAW_INTERVAL: Duration = Duration::from_secs(1);
 
 pub enum View {
     Dashboard,
@@ -35,24 +56,42 @@ pub enum Action {
     OpenDashboard,
     OpenCalibrateCo2Popup,
     OpenCalibrateTemperaturePopup,
+    OpenCalibrateHumidityPopup,
+    OpenCalibrationLogPopup,
     CalibrateCo2,
+    /// Calibrates against the reading of a reference instrument
+    Calibrate(Quantity, f32),
+    SelectDevice(usize),
+    NextDevice,
+    PreviousDevice,
 }
 
 pub struct TerminalUi {
     pub state: Arc<RwLock<View>>,
-    history: Arc<RwLock<History>>,
+    devices: Arc<Devices>,
+    visible: AtomicBool,
 }
 
 impl TerminalUi {
     pub fn start_event_polling(
         self: Arc<Self>,
-        ble_sender: Sender<BleAction>,
         terminal: Arc<Mutex<Terminal<CrosstermBackend<Stdout>>>>,
     ) -> tokio::task::JoinHandle<std::io::Result<()>> {
+        self.visible.store(true, Ordering::Relaxed);
         let me = Arc::clone(&self);
         tokio::task::spawn(async move {
+            let mut last_draw = Instant::now();
+
             loop {
-                if crossterm::event::poll(std::time::Duration::from_millis(16))? {
+                if !crossterm::event::poll(std::time::Duration::from_millis(16))? {
+                    if last_draw.elapsed() > IDLE_REDRAW_INTERVAL {
+                        me.draw(&mut terminal.lock().unwrap());
+                        last_draw = Instant::now();
+                    }
+
+                    // the poll blocks the worker, let the device tasks run on small machines
+                    tokio::task::yield_now().await;
+                } else {
                     if let Event::Key(key) = event::read()? {
                         match key.code {
                             KeyCode::Char('c')
@@ -64,13 +103,10 @@ impl TerminalUi {
                                 std::process::exit(0);
                             }
                             keycode => {
-                                let action = {
-                                    let state_read = &me.state.read().unwrap();
-
-                                    match state_read.deref() {
-                                        View::Dashboard => handle_dashboard_key_event(keycode),
-                                        View::Calibrate(ref popup) => popup.handle_key(key),
-                                    }
+                                let readiness = me.co2_readiness();
+                                let action = match &mut *me.state.write().unwrap() {
+                                    View::Dashboard => handle_dashboard_key_event(keycode),
+                                    View::Calibrate(popup) => popup.handle_key(key, readiness),
                                 };
 
                                 match action {
@@ -78,25 +114,32 @@ impl TerminalUi {
                                         *me.state.write().unwrap() =
                                             View::Calibrate(CalibrationPopup::temperature());
                                     }
+                                    Some(Action::OpenCalibrateHumidityPopup) => {
+                                        *me.state.write().unwrap() =
+                                            View::Calibrate(CalibrationPopup::humidity());
+                                    }
                                     Some(Action::OpenCalibrateCo2Popup) => {
                                         *me.state.write().unwrap() =
                                             View::Calibrate(CalibrationPopup::co2());
                                     }
-                                    Some(Action::CalibrateCo2) => {
-                                        if let Err(err) =
-                                            ble_sender.send(BleAction::CalibrateCo2).await
-                                        {
-                                            tracing::error!(
-                                                "Failed to send CO2 calibration request: {:?}",
-                                                err
-                                            );
+                                    Some(Action::OpenCalibrationLogPopup) => {
+                                        if let Some(device) = me.devices.selected() {
+                                            *me.state.write().unwrap() =
+                                                View::Calibrate(CalibrationPopup::log(&device.id));
                                         }
-
-                                        *me.state.write().unwrap() = View::Dashboard
+                                    }
+                                    Some(Action::CalibrateCo2) => Arc::clone(&me).calibrate_co2(),
+                                    Some(Action::Calibrate(quantity, reference)) => {
+                                        Arc::clone(&me).calibrate(quantity, reference)
                                     }
                                     Some(Action::Reconnect) => {
-                                        ble_sender.send(BleAction::Stop).await.unwrap();
+                                        if let Some(device) = me.devices.selected() {
+                                            device.send(BleAction::Stop).await;
+                                        }
                                     }
+                                    Some(Action::SelectDevice(index)) => me.devices.select(index),
+                                    Some(Action::NextDevice) => me.devices.select_next(),
+                                    Some(Action::PreviousDevice) => me.devices.select_previous(),
                                     Some(Action::ClearHistory) => {
                                         todo!()
                                     }
@@ -112,16 +155,80 @@ impl TerminalUi {
                         }
 
                         me.draw(&mut terminal.lock().unwrap());
+                        last_draw = Instant::now();
                     }
                 }
             }
         })
     }
 
-    pub fn new(history: Arc<RwLock<History>>) -> Result<Self, Box<dyn Error>> {
+    /// Shows the progress in the calibration popup, unless the user closed it meanwhile
+    fn set_calibration_progress(&self, progress: CalibrationProgress) {
+        if let View::Calibrate(popup) = &mut *self.state.write().unwrap() {
+            popup.set_progress(progress);
+        }
+    }
+
+    /// Calibrates CO2 of the selected device in the background, the popup shows the outcome
+    fn calibrate_co2(self: Arc<Self>) {
+        let device = match self.devices.selected() {
+            Some(device) => device,
+            None => return,
+        };
+
+        self.set_calibration_progress(CalibrationProgress::Running);
+        tokio::spawn(async move {
+            let progress = match calibration::calibrate_co2(&device).await {
+                Ok(()) => CalibrationProgress::Done(format!(
+                    "Calibrated, {} takes the current CO2 level as 400 ppm",
+                    device.label
+                )),
+                Err(e) => CalibrationProgress::Failed(e.to_string()),
+            };
+            self.set_calibration_progress(progress);
+        });
+    }
+
+    /// Calibrates the selected device in the background, the popup shows the outcome
+    fn calibrate(self: Arc<Self>, quantity: Quantity, reference: f32) {
+        let device = match self.devices.selected() {
+            Some(device) => device,
+            None => return,
+        };
+
+        self.set_calibration_progress(CalibrationProgress::Running);
+        tokio::spawn(async move {
+            let progress = match calibration::calibrate(&device, quantity, reference, None).await {
+                Ok(adjust) => CalibrationProgress::Done(format!(
+                    "Calibrated, {} now adjusts its {} by {adjust:+}{}",
+                    device.label,
+                    quantity.name(),
+                    quantity.unit()
+                )),
+                Err(e) => CalibrationProgress::Failed(e.to_string()),
+            };
+            self.set_calibration_progress(progress);
+        });
+    }
+
+    /// Readiness of the CO2 wizard for the selected device. The view is always locked before
+    /// the history, a device waiting to write its history would otherwise block the UI.
+    fn co2_readiness(&self) -> Option<Co2Readiness> {
+        match &*self.state.read().unwrap() {
+            View::Calibrate(popup) => {
+                let device = self.devices.selected()?;
+                let history = device.history.read().unwrap();
+                popup.co2_readiness(&history)
+            }
+            View::Dashboard => None,
+        }
+    }
+
+    pub fn new(devices: Arc<Devices>) -> Result<Self> {
         Ok(Self {
-            history,
+            devices,
             state: Arc::new(RwLock::new(View::Dashboard)),
+            visible: AtomicBool::new(false),
         })
     }
 
@@ -130,17 +237,62 @@ impl TerminalUi {
             return;
         }
 
+        // nothing to draw on until the event polling took over the terminal
+        if !self.visible.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let devices = self.devices.all();
+        let selected = match self.devices.selected() {
+            Some(selected) => selected,
+            None => return,
+        };
+
         terminal
             .draw(|f| {
-                match *self.state.read().unwrap() {
-                    View::Dashboard => {
-                        DashboardView::render_dashboard(&self.history.read().unwrap(), f)
-                    }
-                    View::Calibrate(ref popup) => {
-                        DashboardView::render_dashboard(&self.history.read().unwrap(), f);
-                        popup.render(f);
-                    }
-                };
+                let mut area = f.area();
+                // the tab bar is only worth the space when there is something to switch to
+                if devices.len() > 1 {
+                    let [tabs_area, dashboard_area] =
+                        Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+                    render_device_tabs(&devices, self.devices.selected_index(), tabs_area, f);
+                    area = dashboard_area;
+                }
+
+                let [dashboard_area, status_area] =
+                    Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+                let [link_area, window_area] = Layout::horizontal([
+                    Constraint::Min(0),
+                    Constraint::Length(WINDOW_STATUS_WIDTH),
+                ])
+                .areas(status_area);
+                let battery = selected.battery.read().unwrap().latest();
+                render_link_status(&selected.link.snapshot(), battery, link_area, f);
+                render_window_status(&self.devices.window, window_area, f);
+                area = dashboard_area;
+
+                // the history stays on the screen while the device is reconnecting
+                if let Some(banner) = connection_banner(&selected) {
+                    let [banner_area, dashboard_area] =
+                        Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+                    render_connection_banner(banner, banner_area, f);
+                    area = dashboard_area;
+                }
+
+                if let Some(calibration) = selected.calibration.read().unwrap().as_ref() {
+                    let [calibration_area, dashboard_area] =
+                        Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+                    render_calibration_status(calibration, calibration_area, f);
+                    area = dashboard_area;
+                }
+
+                // the same order as the key handling takes the locks in
+                let view = self.state.read().unwrap();
+                let history = selected.history.read().unwrap();
+                DashboardView::render_dashboard(&history, f, area);
+                if let View::Calibrate(ref popup) = *view {
+                    popup.render(&history, f);
+                }
             })
             .unwrap();
     }
@@ -0,0 +1,47 @@
+use super::link_status::battery_span;
+use crate::{connection::ConnectionState, window_sensor::WindowSensor};
+use ratatui::{
+    layout::{Alignment, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+
+/// Room for the longest text rendered by [`render_window_status`]
+pub const WINDOW_STATUS_WIDTH: u16 = 40;
+
+/// Current state of the window sensor, nothing until it reported for the first time
+pub fn render_window_status(sensor: &WindowSensor, area: Rect, f: &mut Frame) {
+    let timeline = sensor.timeline.read().unwrap();
+    let (is_closed, since) = match (timeline.is_closed(), timeline.since()) {
+        (Some(is_closed), Some(since)) => (is_closed, since),
+        _ => return,
+    };
+
+    let state_style = if sensor.state() == ConnectionState::Streaming {
+        Style::default().add_modifier(Modifier::BOLD)
+    } else {
+        // the state is only as fresh as the last report of the lost sensor
+        Style::default().fg(Color::DarkGray)
+    };
+
+    let mut spans = vec![
+        Span::from("Window "),
+        Span::styled(if is_closed { "closed" } else { "open" }, state_style),
+        Span::from(format!(
+            " since {} ",
+            since.format("%H:%M").unwrap_or_default()
+        )),
+    ];
+    if let Some(level) = sensor.battery.read().unwrap().latest() {
+        spans.extend([Span::from("battery "), battery_span(level), Span::from(" ")]);
+    }
+
+    f.render_widget(
+        Paragraph::new(Line::from(spans))
+            .alignment(Alignment::Right)
+            .style(Style::default().fg(Color::Gray)),
+        area,
+    )
+}
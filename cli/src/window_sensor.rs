@@ -0,0 +1,154 @@
+use crate::{
+    battery,
+    bluetooth::{self, FromBleData, LinkStats},
+    config::{self, settings::Settings},
+    connection::{Backoff, ConnectionState},
+    error::{Error, Result},
+    history::{BatteryHistory, WindowTimeline},
+};
+use btleplug::api::Peripheral;
+use std::{
+    sync::{Arc, RwLock},
+    time::Instant,
+};
+use tokio::time::sleep;
+
+#[derive(Debug, Clone, Copy)]
+pub struct WindowState {
+    pub is_closed: bool,
+}
+
+impl FromBleData for WindowState {
+    fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        match data.first() {
+            Some(state) => Ok(Self {
+                is_closed: *state == 1,
+            }),
+            None => Err(Error::Decode {
+                payload: data,
+                reason: "empty window state".to_string(),
+            }),
+        }
+    }
+}
+
+/// The window sensor is connected once and streams its state changes, instead of being
+/// looked up every time somebody needs to know whether the window is open
+pub struct WindowSensor {
+    pub timeline: RwLock<WindowTimeline>,
+    pub battery: RwLock<BatteryHistory>,
+    pub link: LinkStats,
+    state: RwLock<ConnectionState>,
+}
+
+impl Default for WindowSensor {
+    fn default() -> Self {
+        Self {
+            timeline: RwLock::new(WindowTimeline::new()),
+            battery: RwLock::new(BatteryHistory::new()),
+            link: LinkStats::default(),
+            state: RwLock::new(ConnectionState::Scanning),
+        }
+    }
+}
+
+impl WindowSensor {
+    pub fn state(&self) -> ConnectionState {
+        *self.state.read().unwrap()
+    }
+
+    fn set_state(&self, state: ConnectionState) {
+        *self.state.write().unwrap() = state;
+    }
+
+    /// Latest known state, `None` until the sensor reported at least once
+    pub fn current(&self) -> Option<WindowState> {
+        self.timeline
+            .read()
+            .unwrap()
+            .is_closed()
+            .map(|is_closed| WindowState { is_closed })
+    }
+
+    fn capture(&self, state: WindowState) {
+        tracing::debug!("Window state: {:?}", state);
+        self.timeline
+            .write()
+            .unwrap()
+            .capture_state(state.is_closed);
+    }
+}
+
+/// Streams the window state until the connection breaks
+async fn stream(
+    sensor: &WindowSensor,
+    connection: &bluetooth::Connection<impl Peripheral>,
+    settings: &Settings,
+) -> Result<()> {
+    let service_uuid = config::ble().window_service;
+    let read_char_uuid = config::ble().window_read_char;
+    // older window firmware exposes the state under the service uuid
+    let char_uuid = if connection.has_characteristic(read_char_uuid) {
+        read_char_uuid
+    } else {
+        service_uuid
+    };
+
+    // notifications only come with the next measurement, so do not wait for it
+    let state = connection
+        .read_from_sensor::<WindowState>(char_uuid)
+        .await?;
+    sensor.capture(state);
+    sensor.set_state(ConnectionState::Streaming);
+
+    let mut on_battery = |level: u8| {
+        let previous = sensor.battery.write().unwrap().capture_level(level);
+        battery::check_level("Window sensor", previous, level, &settings.battery_alerts);
+    };
+
+    tokio::select! {
+        result = connection.subscribe(char_uuid, &sensor.link, |state| sensor.capture(state)) => result,
+        result = battery::watch(connection, &mut on_battery) => result,
+    }
+}
+
+/// Keeps the window sensor connected for the lifetime of the app
+pub async fn run(sensor: Arc<WindowSensor>, settings: Arc<Settings>) {
+    let mut backoff = Backoff::new();
+    let mut connected_before = false;
+
+    loop {
+        let on_state = {
+            let sensor = Arc::clone(&sensor);
+            move |state| sensor.set_state(state)
+        };
+
+        let ble = config::ble();
+        match bluetooth::connect_to(&ble.window_local_name, ble.window_service, &on_state).await {
+            Ok(connection) => {
+                if connected_before {
+                    sensor.link.record_reconnect();
+                }
+                connected_before = true;
+
+                if let Err(e) = stream(&sensor, &connection, &settings).await {
+                    tracing::error!("Window sensor connection failed: {e}");
+                }
+
+                if sensor.state() == ConnectionState::Streaming {
+                    backoff.reset();
+                }
+                connection.disconnect_with_timeout().await;
+            }
+            // the sensor only advertises for a few seconds every half a minute
+            Err(e) => tracing::debug!("Window sensor not connected: {e}"),
+        }
+
+        let delay = backoff.next_delay();
+        sensor.set_state(ConnectionState::BackingOff {
+            attempt: backoff.attempt(),
+            retry_at: Instant::now() + delay,
+        });
+        sleep(delay).await;
+    }
+}
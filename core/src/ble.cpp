@@ -11,6 +11,7 @@
 
 struct BleState {
   bool hasBleConnection = false;
+  uint16_t connectionId = 0;
 };
 
 class BleServerCallbacks : public BLEServerCallbacks {
@@ -19,8 +20,9 @@ class BleServerCallbacks : public BLEServerCallbacks {
 public:
   BleServerCallbacks(BleState *pstate) { this->bleState = pstate; };
 
-  void onConnect(BLEServer *pServer) {
+  void onConnect(BLEServer *pServer, esp_ble_gatts_cb_param_t *param) {
     this->bleState->hasBleConnection = true;
+    this->bleState->connectionId = param->connect.conn_id;
     Serial.println("***** Connect");
   }
 
@@ -39,10 +41,17 @@ public:
     this->calibration = calibration;
   };
 
+  // The value keeps reporting isCalibrated() to the next connections, the
+  // outcome of this write follows it as a fifth byte
   void onWrite(BLECharacteristic *pCharacteristic) {
-    int result = calibration->calibrate(pCharacteristic->getData(),
-                                        pCharacteristic->getLength());
-    pCharacteristic->setValue(result);
+    bool accepted = calibration->calibrate(pCharacteristic->getData(),
+                                           pCharacteristic->getLength());
+    int32_t isCalibrated = calibration->isCalibrated();
+
+    uint8_t value[sizeof(isCalibrated) + 1];
+    memcpy(value, &isCalibrated, sizeof(isCalibrated)); // little-endian
+    value[sizeof(isCalibrated)] = accepted;
+    pCharacteristic->setValue(value, sizeof(value));
   }
 };
 
@@ -96,6 +105,10 @@ public:
         pService, BLEUUID(BLE_MAIN_SENSOR_TEMP_CALIBRATION_CHAR),
         &calibration->temperatureCalibration);
 
+    BLECharacteristic *humidity_char = this->create_calibration_characteristic(
+        pService, BLEUUID(BLE_MAIN_SENSOR_HUMIDITY_CALIBRATION_CHAR),
+        &calibration->humidityCalibration);
+
     pService->start();
 
     // Start advertising
@@ -104,11 +117,28 @@ public:
     pAdvertising->start();
   };
 
+  // Messages bigger than the negotiated MTU are split into consecutive
+  // notifications, the CLI puts them back together
+  void notifyInChunks(uint8_t *payload, size_t size) {
+    // every notification carries a 3 byte ATT header
+    size_t chunkSize = pServer->getPeerMTU(bleState.connectionId) - 3;
+    for (size_t offset = 0; offset < size; offset += chunkSize) {
+      this->dataCharacteristic->setValue(payload + offset,
+                                         min(chunkSize, size - offset));
+      this->dataCharacteristic->notify();
+    }
+  }
+
   void notify(ClimateData *data, ErrorBitFlags *errorFlags) {
     if (this->bleState.hasBleConnection) {
-      this->dataCharacteristic->setValue(
-          serializeClimateData(data, errorFlags));
-      this->dataCharacteristic->notify();
+#ifdef SERIALIZE_BINARY
+      uint8_t payload[CLIMATE_DATA_BINARY_V1_SIZE];
+      size_t size = encodeClimateData(data, errorFlags, payload);
+      this->notifyInChunks(payload, size);
+#else
+      const char *payload = serializeClimateData(data, errorFlags);
+      this->notifyInChunks((uint8_t *)payload, strlen(payload));
+#endif
     }
   }
 };
\ No newline at end of file
@@ -3,12 +3,16 @@
 #include <Preferences.h>
 #include <cstddef>
 #include <stdint.h>
+#include <string.h>
 
 class Calibrator {
 public:
   pSensors sensors;
   Preferences preferences;
 
+  // nothing is read or persisted until the namespace is opened
+  Calibrator() { this->preferences.begin("calibration", false); }
+
   virtual bool calibrate(uint8_t *value, size_t size) = 0;
   virtual bool isCalibrated() = 0;
   virtual void adjustMeasurement(ClimateData *data) {};
@@ -21,16 +25,31 @@ public:
   }
 
   bool calibrate(uint8_t *value, size_t size) {
+    // a stray write must not zero the sensor in a stuffy room
+    size_t commandSize = strlen(BLE_MAIN_SENSOR_CALIBRATE_CO2);
+    if (value == NULL || size != commandSize ||
+        memcmp(value, BLE_MAIN_SENSOR_CALIBRATE_CO2, commandSize) != 0) {
+      Serial.println("Unknown CO2 calibration command");
+      return false;
+    }
+
     Serial.println("Calibrating CO2 sensor");
     this->sensors.mhz19->calibrate();
+    if (this->sensors.mhz19->errorCode != RESULT_OK) {
+      return false;
+    }
     this->preferences.putBool("co2_calibrated", true);
 
     return true;
   }
 };
 
-class TemperatureCalibration : public Calibrator {
-  int temperature_adjust = CALIBRATION_TEMPERATURE_ADJUST;
+// Adds a whole number offset to a measurement, the offset is written over BLE
+// as a little-endian int32 and persisted under the given key, NVS keys are at
+// most 15 characters long
+class AdjustCalibration : public Calibrator {
+  const char *key;
+
   bool deserialize_ble_data(uint8_t *data, size_t dataSize, int32_t &dest) {
     if (data == NULL || dataSize < sizeof(int32_t)) {
       return false; // Error: data is NULL or not enough data to convert
@@ -43,43 +62,63 @@ class TemperatureCalibration : public Calibrator {
     return true;
   }
 
+protected:
+  int adjust;
+
 public:
-  TemperatureCalibration() {
-    this->temperature_adjust = this->preferences.getInt(
-        "bmp280_calibration", CALIBRATION_TEMPERATURE_ADJUST);
+  AdjustCalibration(const char *key, int defaultAdjust) {
+    this->key = key;
+    this->adjust = this->preferences.getInt(key, defaultAdjust);
   };
 
-  bool isCalibrated() {
-    return this->preferences.getInt("bmp280_calibration", -2587) != -2587;
-  }
+  bool isCalibrated() { return this->preferences.isKey(this->key); }
 
   bool calibrate(uint8_t *value, size_t size) {
-    int32_t temp;
-    if (!deserialize_ble_data(value, size, temp)) {
+    int32_t adjust;
+    if (!deserialize_ble_data(value, size, adjust)) {
       return false;
     }
 
-    this->temperature_adjust = temp;
-    this->preferences.putInt("bmp280_calibration", temp);
+    // putInt returns the bytes written, 0 when the value was not persisted
+    if (this->preferences.putInt(this->key, adjust) != sizeof(int32_t)) {
+      return false;
+    }
+    this->adjust = adjust;
 
     return true;
   }
-  void adjustMeasurement(ClimateData *data) {
-    data->temperature += temperature_adjust;
-  }
+};
+
+class TemperatureCalibration : public AdjustCalibration {
+public:
+  TemperatureCalibration()
+      : AdjustCalibration("temp_adjust", CALIBRATION_TEMPERATURE_ADJUST) {}
+
+  void adjustMeasurement(ClimateData *data) { data->temperature += adjust; }
+};
+
+class HumidityCalibration : public AdjustCalibration {
+public:
+  HumidityCalibration()
+      : AdjustCalibration("hum_adjust", CALIBRATION_HUMIDITY_ADJUST) {}
+
+  void adjustMeasurement(ClimateData *data) { data->humidity += adjust; }
 };
 
 class Calibration {
 public:
   MHZ19Calibration mhz19Calibration;
   TemperatureCalibration temperatureCalibration;
+  HumidityCalibration humidityCalibration;
   Calibration(pSensors sensors) {
     this->mhz19Calibration.sensors = sensors;
     this->temperatureCalibration.sensors = sensors;
+    this->humidityCalibration.sensors = sensors;
   }
 
   void adjustMeasurement(ClimateData *data) {
     this->mhz19Calibration.adjustMeasurement(data);
     this->temperatureCalibration.adjustMeasurement(data);
+    this->humidityCalibration.adjustMeasurement(data);
   }
 };
\ No newline at end of file
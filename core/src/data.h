@@ -16,7 +16,7 @@ struct pSensors {
 };
 
 struct ErrorBitFlags {
-  ERRORCODE mhz19;
+  ERRORCODE mhz19 = RESULT_OK;
   bool bmp280 = false;
   bool bh1750 = false;
   uint16_t ccs811;
@@ -119,6 +119,8 @@ void loop() {
     ClimateData data;
 
     data.light = lightSensor.readLightLevel();
+    // negative levels are the error codes of the library
+    errorFlags.bh1750 = data.light < 0;
     Serial.print("Light Intensity: ");
     Serial.print(data.light);
     Serial.println(" lux");
@@ -129,11 +131,12 @@ void loop() {
     Serial.print(" °C, ");
 
     data.pressure = bmp280.readPressure() / 100;
+    errorFlags.bmp280 = isnan(data.temperature) || isnan(data.pressure);
     Serial.print("Pressure = ");
     Serial.print(data.pressure / 100);
     Serial.println(" Pa, ");
 
-    data.humidity = SI702x.readHumidity() + 5;
+    data.humidity = SI702x.readHumidity();
     Serial.print("SI702x => Temperature = ");
     Serial.print(SI702x.readTemperature(), 2);
     Serial.print(" °C, ");
@@ -172,6 +175,8 @@ void loop() {
     }
 
     calibration->adjustMeasurement(&data);
+    // always, a unit plugged in over USB has no BLE central connected
+    printClimateData(&data, &errorFlags);
     bleProtocol.notify(&data, &errorFlags);
 #if ENABLE_PROMETHEUS
     prometheus.maybeInit(); // retries deferred init; no-op once running
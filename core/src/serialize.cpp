@@ -22,8 +22,8 @@ uint32_t encodeTO2Errors(ErrorBitFlags *errorFlags) {
 #ifdef SERIALIZE_JSON
 #include <FirebaseJson.h>
 
-const char *serializeClimateData(ClimateData *data, ErrorBitFlags *errorFlags) {
-  FirebaseJson json;
+static void setClimateData(FirebaseJson &json, ClimateData *data,
+                           ErrorBitFlags *errorFlags) {
   json.set("temperature", data->temperature);
   json.set("pressure", data->pressure);
   json.set("humidity", data->humidity);
@@ -36,8 +36,54 @@ const char *serializeClimateData(ClimateData *data, ErrorBitFlags *errorFlags) {
   if (errors > 0) {
     json.set("error_flags", errors);
   }
+}
 
-  json.toString(Serial, true);
+const char *serializeClimateData(ClimateData *data, ErrorBitFlags *errorFlags) {
+  FirebaseJson json;
+  setClimateData(json, data, errorFlags);
   return json.raw();
 }
-#endif
\ No newline at end of file
+
+void printClimateData(ClimateData *data, ErrorBitFlags *errorFlags) {
+  FirebaseJson json;
+  setClimateData(json, data, errorFlags);
+  json.toString(Serial);
+  Serial.println();
+}
+#endif
+
+// Little endian, missing readings are sent as 0xFFFF:
+//  0     format (CLIMATE_DATA_FORMAT_BINARY_V1)
+//  1-2   temperature, int16 in 0.01 °C
+//  3-4   pressure, uint16 in 0.1 hPa
+//  5-6   humidity, uint16 in 0.01 %
+//  7-8   light, uint16 in lux
+//  9-10  co2, uint16 in ppm
+//  11-12 eco2, uint16 in ppm
+//  13-14 etvoc, uint16 in ppb
+//  15-16 error flags, see encodeTO2Errors
+static void writeUint16(uint8_t *buffer, size_t offset, uint16_t value) {
+  buffer[offset] = value & 0xFF;
+  buffer[offset + 1] = value >> 8;
+}
+
+static uint16_t toUint16(float value) {
+  if (value < 0 || value >= 0xFFFF) {
+    return 0xFFFF;
+  }
+  return (uint16_t)roundf(value);
+}
+
+size_t encodeClimateData(ClimateData *data, ErrorBitFlags *errorFlags,
+                         uint8_t *buffer) {
+  buffer[0] = CLIMATE_DATA_FORMAT_BINARY_V1;
+  writeUint16(buffer, 1, (uint16_t)(int16_t)roundf(data->temperature * 100));
+  writeUint16(buffer, 3, toUint16(data->pressure * 10));
+  writeUint16(buffer, 5, toUint16(data->humidity * 100));
+  writeUint16(buffer, 7, toUint16(data->light));
+  writeUint16(buffer, 9, data->co2 > 0 ? toUint16(data->co2) : 0xFFFF);
+  writeUint16(buffer, 11, data->eco2);
+  writeUint16(buffer, 13, data->etvoc);
+  writeUint16(buffer, 15, encodeTO2Errors(errorFlags));
+  return CLIMATE_DATA_BINARY_V1_SIZE;
+}
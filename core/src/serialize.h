@@ -1,7 +1,19 @@
 #include "data.h"
 
 #define SERIALIZE_JSON
+// notify the compact binary payload instead of JSON, the CLI understands both
+// #define SERIALIZE_BINARY
+
+// first byte of a binary payload, JSON payloads always start with '{'
+#define CLIMATE_DATA_FORMAT_BINARY_V1 0x01
+#define CLIMATE_DATA_BINARY_V1_SIZE 17
 
 uint32_t encodeTO2Errors(ErrorBitFlags *errorFlags);
 
 const char *serializeClimateData(ClimateData *data, ErrorBitFlags *errorFlags);
+
+// one JSON object per line on Serial, read by `co2nsole --serial`
+void printClimateData(ClimateData *data, ErrorBitFlags *errorFlags);
+
+size_t encodeClimateData(ClimateData *data, ErrorBitFlags *errorFlags,
+                         uint8_t *buffer);
@@ -6,10 +6,11 @@
   "beb5483e-36e1-4688-b7f5-ea07361b26a8"
 #define BLE_MAIN_SENSOR_TEMP_CALIBRATION_CHAR                                  \
   "d753f24d-3aa0-4678-b039-a52d3b2e3946"
+#define BLE_MAIN_SENSOR_HUMIDITY_CALIBRATION_CHAR                              \
+  "c71d016d-31a8-4fbc-b47b-ad3f05796210"
 
 #define BLE_MAIN_SENSOR_CALIBRATE_CO2 "CalibrateMhZ19"
 #define BLE_MAIN_SENSOR_CALIBRATE_TEMPERATURE "CalibrateTemperature"
-#define BLE_MAIN_SENSOR_CALIBRATE_HUMIDITY "CalibrateHumidity"
 
 // Window sensor BLE
 #define BLE_WINDOW_SERVICE_LOCAL_NAME "co2nsole window"
@@ -19,6 +20,7 @@
 // Calibrator defaults
 #define CALIBRATION_CO2_DEFAULT 400
 #define CALIBRATION_TEMPERATURE_ADJUST -8
+#define CALIBRATION_HUMIDITY_ADJUST 5
 
 // ---------------------------------------------------------------------------
 // Prometheus remote-write (climate metrics fan-out)
@@ -14,8 +14,9 @@ const int MAX_PERCENTAGE = 100; // Maximum percentage value
 
 class Battery {
 public:
+  // standard Battery Service (0x180F), the level is read by the CLI
+  BLEBas batteryLevelService;
   BLEService batteryBleService;
-  BLECharacteristic chargeCharacteristic;
   BLECharacteristic isChargingCharacteristic;
   float lastChargePercentage;
 
@@ -31,8 +32,6 @@ Battery::Battery() {
   pinMode(BAT_CHARGE_STATE, INPUT);
 
   batteryBleService = BLEService("1a1a1963-7e2b-45ed-a1f7-82d01423e841");
-  chargeCharacteristic =
-      BLECharacteristic("aaa8d3ef-4c86-47a2-9428-6bdcf6041e3e");
   isChargingCharacteristic =
       BLECharacteristic("d6bc7685-31c6-4fd7-843f-9e74237ca2d2");
 
@@ -47,20 +46,21 @@ void startCharacteristic(BLECharacteristic &characteristic) {
 }
 
 void Battery::begin() {
+  batteryLevelService.begin();
+  batteryLevelService.write(100);
+
   Bluefruit.Advertising.addService(batteryBleService);
   batteryBleService.begin();
 
-  startCharacteristic(chargeCharacteristic);
   startCharacteristic(isChargingCharacteristic);
 
   lastChargePercentage = 100.0;
-  this->chargeCharacteristic.notify8(100.0);
 }
 
 // Function to map the battery voltage to a percentage
 uint8_t mapVoltageToPercentage(float voltage) {
   if (voltage < MIN_VOLTAGE) {
-    return -1;
+    return MIN_PERCENTAGE;
   }
 
   // My battery is kind of jank and old so the values are experimental while the
@@ -89,7 +89,7 @@ void Battery::updateBatteryState() {
   this->isChargingCharacteristic.notify8(isCharging);
 
   if (percentage < lastChargePercentage || isCharging) {
-    this->chargeCharacteristic.notify8(percentage);
+    this->batteryLevelService.notify(percentage);
     Serial.println("Notified");
     lastChargePercentage = percentage;
   }
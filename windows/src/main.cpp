@@ -10,7 +10,7 @@ SoftwareTimer mainTimer;
 Adafruit_VL53L0X lox = Adafruit_VL53L0X();
 
 BLEService service(BLE_WINDOW_SENSOR_SERVICE);
-BLECharacteristic characteristic(BLE_WINDOW_SENSOR_SERVICE);
+BLECharacteristic characteristic(BLE_WINDOW_SENSOR_READ_CHAR);
 
 BLEDis bledis; // DIS (Device Information Service) helper class instance
 Battery battery;
@@ -87,22 +87,19 @@ void measurementCallback(TimerHandle_t xTimerID) {
 
   characteristic.write8(notify_value);
 
-  digitalWrite(LED_BLUE, LOW);
-  Bluefruit.Advertising.start();
-  delay(5000);
-  digitalWrite(LED_BLUE, HIGH);
-
   if (Bluefruit.connected()) {
-    Serial.println("BLE connected, writing and waiting for disconnect");
+    // the CLI keeps a long-lived subscription, just push the new state to it
+    Serial.println("BLE connected, notifying");
+    characteristic.notify8(notify_value);
+  } else {
+    digitalWrite(LED_BLUE, LOW);
+    Bluefruit.Advertising.start();
+    delay(5000);
+    digitalWrite(LED_BLUE, HIGH);
 
-    while (Bluefruit.connected()) {
-      delay(100);
-      digitalWrite(LED_BLUE, !digitalRead(LED_BLUE));
+    if (!Bluefruit.connected()) {
+      Bluefruit.Advertising.stop();
     }
-
-    digitalWrite(LED_BLUE, HIGH);
-  } else {
-    Bluefruit.Advertising.stop();
   }
 
   xTimerStart(xTimerID, 0);